@@ -0,0 +1,55 @@
+//! A baseline for the speed of the board and the engine, to compare changes
+//! of the board representation or the search against: `cargo bench`.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+
+use connect4::{
+    ai,
+    game::{Game, Rules},
+    headless,
+};
+
+/// A full 6x7 board whose only line is in the top row, so finding it means
+/// going through every field below.
+const FULL_WON: &str = "BBRRRRB\nBBRRBBR\nRRBBRRB\nBBRRBBR\nRRBBRRB\nBBRRBBR\n";
+
+/// A board with its top row still open, so every lane can be played.
+const TOP_ROW_OPEN: &str = ".......\nRRBBRRB\nBBRRBBR\nRRBBRRB\nBBRRBBR\nRRBBRRB\n";
+
+fn win_detection(c: &mut Criterion) {
+    let full = Game::from_board_str(FULL_WON, Rules::default()).unwrap();
+    assert!(full.winning_line().is_some());
+    c.bench_function("winning line on a full board", |b| {
+        b.iter(|| black_box(&full).winning_line())
+    });
+    c.bench_function("reading a full board", |b| {
+        b.iter(|| Game::from_board_str(black_box(FULL_WON), Rules::default()))
+    });
+
+    let open = Game::from_board_str(TOP_ROW_OPEN, Rules::default()).unwrap();
+    c.bench_function("winning drops with the top row open", |b| {
+        b.iter(|| black_box(&open).winning_drops(open.turn()))
+    });
+}
+
+fn search(c: &mut Criterion) {
+    let start = Game::new();
+    c.bench_function("best move from the empty board at depth 6", |b| {
+        b.iter(|| ai::best_move(black_box(&start), 6))
+    });
+}
+
+fn self_play(c: &mut Criterion) {
+    let start = Game::new();
+    let mut group = c.benchmark_group("self-play");
+    // Reported as games per second.
+    group.throughput(Throughput::Elements(1));
+    group.sample_size(10);
+    group.bench_function("one game at depths 4,4", |b| {
+        b.iter(|| headless::self_play(black_box(&start), 1, (4, 4)))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, win_detection, search, self_play);
+criterion_main!(benches);
@@ -0,0 +1,650 @@
+use std::{
+    collections::HashMap,
+    sync::mpsc::{self, Receiver},
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+use crate::game::{Field, Game, Move, MoveKind, Turn};
+
+/// Score of a won position, before adjusting for how soon the win happens.
+const WIN: i32 = 1_000_000;
+
+pub const DEFAULT_TIME: Duration = Duration::from_millis(1000);
+
+/// How strongly the computer plays.
+#[derive(Copy, Clone, Default, PartialEq)]
+pub enum Difficulty {
+    /// A shallow search that only sees wins and losses coming, picking at
+    /// random among the moves it cannot tell apart.
+    Easy,
+    /// A search to a fixed depth with the full evaluation.
+    Medium,
+    /// Searches as deep as the thinking time allows.
+    #[default]
+    Hard,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 3] = [Difficulty::Easy, Difficulty::Medium, Difficulty::Hard];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Medium => "medium",
+            Difficulty::Hard => "hard",
+        }
+    }
+}
+
+/// Search depths of [`Difficulty::Easy`] and [`Difficulty::Medium`].
+const EASY_DEPTH: u32 = 2;
+const MEDIUM_DEPTH: u32 = 4;
+
+/// Value of a disc in each column of a board `width` columns wide: central
+/// discs take part in the most lines, so the weight falls by one per column
+/// towards the edges, e.g. `[0, 1, 2, 3, 2, 1, 0]` for 7 columns.
+pub fn column_weights(width: usize) -> Vec<i32> {
+    let last = width as i32 - 1;
+    (0..width as i32)
+        .map(|column| (last - (2 * column - last).abs()) / 2)
+        .collect()
+}
+
+/// Evaluation drop, from the mover's side, that marks a move as a blunder.
+const BLUNDER_SWING: i32 = 20;
+
+/// A lane picked by the search and its score for the player who moves there.
+#[derive(Copy, Clone)]
+pub struct Choice {
+    pub lane: usize,
+    /// `None` when no search round finished in time, or on
+    /// [`Difficulty::Easy`], whose scores only tell wins from losses.
+    pub score: Option<i32>,
+}
+
+/// Depth of the search behind the analysis readout.
+pub const ANALYSIS_DEPTH: u32 = 6;
+/// Moves of the principal variation worth showing.
+const LINE_LENGTH: usize = 5;
+
+/// The engine's view of a position, see [`analyse`].
+pub struct Analysis {
+    /// Score for Red, as in [`format_eval`].
+    pub eval: i32,
+    /// The expected line of best play as lanes, starting with the player to
+    /// move.
+    pub line: Vec<usize>,
+}
+
+/// Progress of a background search started with [`spawn_search`].
+pub enum SearchEvent {
+    /// Every move was searched to this depth.
+    Completed { depth: u32 },
+    /// The chosen lane, or `None` when there is nothing to play.
+    Finished(Option<Choice>),
+}
+
+/// Picks a move at `difficulty` on another thread, reporting progress on
+/// the returned channel. Only [`Difficulty::Hard`] searches for the whole
+/// `budget`, with [`best_move_within`].
+pub fn spawn_search(game: Game, difficulty: Difficulty, budget: Duration) -> Receiver<SearchEvent> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let completed = |depth| {
+            let _ = sender.send(SearchEvent::Completed { depth });
+        };
+        let choice = match difficulty {
+            Difficulty::Easy => {
+                let nanos = SystemTime::now()
+                    .duration_since(SystemTime::UNIX_EPOCH)
+                    .map_or(0, |time| time.subsec_nanos());
+                let choice = easy_move(&game, game.hash() ^ u64::from(nanos));
+                completed(EASY_DEPTH);
+                choice
+            }
+            Difficulty::Medium => {
+                let choice = Searcher::default()
+                    .root(&mut game.clone(), MEDIUM_DEPTH)
+                    .map(|(lane, score)| Choice {
+                        lane,
+                        score: Some(score),
+                    });
+                completed(MEDIUM_DEPTH);
+                choice
+            }
+            Difficulty::Hard => best_move_within(&game, Instant::now() + budget, completed),
+        };
+        let _ = sender.send(SearchEvent::Finished(choice));
+    });
+    receiver
+}
+
+/// A [`Difficulty::Easy`] move: one of the lanes scoring best in a shallow
+/// search that sees nothing but wins and losses, picked by `seed`.
+fn easy_move(game: &Game, seed: u64) -> Option<Choice> {
+    let mut game = game.clone();
+    let mut searcher = Searcher {
+        tactics_only: true,
+        ..Searcher::default()
+    };
+    let mut scored = Vec::new();
+    for lane in move_order(&game) {
+        game.drop(lane).ok()?;
+        let score = -searcher.negamax(&mut game, EASY_DEPTH - 1, -i32::MAX, i32::MAX);
+        game.undo();
+        scored.push((lane, score));
+    }
+    let best = scored.iter().map(|&(_, score)| score).max()?;
+    scored.retain(|&(_, score)| score == best);
+    let (lane, _) = scored[(seed % scored.len() as u64) as usize];
+    Some(Choice { lane, score: None })
+}
+
+/// Evaluates `game` to `depth` and follows the best moves from there, each
+/// searched one ply shallower, for the principal variation. `None` when
+/// there is nothing to play.
+pub fn analyse(game: &Game, depth: u32) -> Option<Analysis> {
+    let mut game = game.clone();
+    let (first, score) = Searcher::default().root(&mut game, depth)?;
+    let eval = match game.turn() {
+        Turn::Red => score,
+        Turn::Blue => -score,
+    };
+    let mut line = vec![first];
+    game.drop(first).ok()?;
+    for remaining in (1..depth).rev().take(LINE_LENGTH - 1) {
+        let Some((lane, _)) = Searcher::default().root(&mut game, remaining) else {
+            break;
+        };
+        line.push(lane);
+        game.drop(lane).ok()?;
+    }
+    Some(Analysis { eval, line })
+}
+
+/// Best play from `game` until it is decided, each move searched to
+/// `depth`: a forced win the way the engine finds it, against the best
+/// defence.
+pub fn best_line(game: &Game, depth: u32) -> Vec<usize> {
+    let mut game = game.clone();
+    let mut line = Vec::new();
+    while let Some((lane, _)) = Searcher::default().root(&mut game, depth) {
+        if game.drop(lane).is_err() {
+            break;
+        }
+        line.push(lane);
+    }
+    line
+}
+
+/// Runs [`analyse`] to [`ANALYSIS_DEPTH`] on another thread.
+pub fn spawn_analysis(game: Game) -> Receiver<Option<Analysis>> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let _ = sender.send(analyse(&game, ANALYSIS_DEPTH));
+    });
+    receiver
+}
+
+/// Picks a lane for the player to move using alpha-beta negamax to a fixed
+/// `depth`, or `None` when there is nothing to play.
+pub fn best_move(game: &Game, depth: u32) -> Option<usize> {
+    Searcher::default()
+        .root(&mut game.clone(), depth)
+        .map(|(column, _)| column)
+}
+
+/// A move for when there is nothing to drop: in Pop Out the pop nearest
+/// the center, otherwise a bomb or anvil there. The search itself only
+/// drops.
+pub fn fallback_move(game: &Game) -> Option<(MoveKind, usize)> {
+    let center = game.width() / 2;
+    if let Some(column) = game
+        .legal_pops()
+        .min_by_key(|column| column.abs_diff(center))
+    {
+        return Some((MoveKind::Pop, column));
+    }
+    MoveKind::POWER_PIECES
+        .into_iter()
+        .find(|&kind| game.pieces_left(game.turn(), kind) > 0)
+        .map(|kind| (kind, center))
+}
+
+/// Iterative deepening: searches one ply deeper each round until `deadline`,
+/// keeping the move of the deepest completed round. `completed` is called with
+/// each finished depth.
+pub fn best_move_within(
+    game: &Game,
+    deadline: Instant,
+    mut completed: impl FnMut(u32),
+) -> Option<Choice> {
+    let mut game = game.clone();
+    let mut best = move_order(&game)
+        .first()
+        .map(|&lane| Choice { lane, score: None });
+    for depth in 1..=game.empty_fields() as u32 {
+        let mut searcher = Searcher {
+            deadline: Some(deadline),
+            ..Searcher::default()
+        };
+        let result = searcher.root(&mut game, depth);
+        if searcher.timed_out {
+            break;
+        }
+        let Some((lane, score)) = result else { break };
+        best = Some(Choice {
+            lane,
+            score: Some(score),
+        });
+        completed(depth);
+        if score.abs() >= WIN - 1 || Instant::now() >= deadline {
+            break;
+        }
+    }
+    best
+}
+
+/// Formats an evaluation for transcripts: tenths of the window score, or a
+/// forced result.
+pub fn format_eval(eval: i32) -> String {
+    if eval.abs() >= WIN - 1 {
+        return if eval > 0 { "+win" } else { "-win" }.to_string();
+    }
+    format!("{:+.1}", eval as f64 / 10.0)
+}
+
+/// Whether the mover's evaluation fell by more than [`BLUNDER_SWING`]
+/// between two evaluations from Red's side.
+pub fn is_blunder(mover: Turn, previous: i32, eval: i32) -> bool {
+    let swing = match mover {
+        Turn::Red => previous.saturating_sub(eval),
+        Turn::Blue => eval.saturating_sub(previous),
+    };
+    swing > BLUNDER_SWING
+}
+
+/// For every move of `history` with an evaluation, the evaluation of the
+/// last evaluated move before it and its own, as compared by [`is_blunder`].
+pub fn eval_changes(history: &[Move]) -> Vec<Option<(i32, i32)>> {
+    let mut previous = None;
+    history
+        .iter()
+        .map(|m| {
+            let eval = m.eval?;
+            previous.replace(eval).map(|previous| (previous, eval))
+        })
+        .collect()
+}
+
+#[derive(Default)]
+struct Searcher {
+    deadline: Option<Instant>,
+    timed_out: bool,
+    /// Whether positions are scored only by the wins they threaten, see
+    /// [`evaluate`].
+    tactics_only: bool,
+    /// Scores of positions already searched this round, by [`Game::hash`].
+    table: HashMap<u64, Entry>,
+}
+
+/// A transposition table entry: what searching a position to `depth` found.
+#[derive(Copy, Clone)]
+struct Entry {
+    depth: u32,
+    score: i32,
+    bound: Bound,
+}
+
+/// How an [`Entry`] score relates to the true score, after alpha-beta cuts.
+#[derive(Copy, Clone, PartialEq)]
+enum Bound {
+    Exact,
+    /// The search stopped at a refutation; the score may be higher.
+    Lower,
+    /// Every move failed low; the score may be lower.
+    Upper,
+}
+
+impl Searcher {
+    fn root(&mut self, game: &mut Game, depth: u32) -> Option<(usize, i32)> {
+        let mut best = None;
+        let mut alpha = -i32::MAX;
+        for column in move_order(game) {
+            game.drop(column).ok()?;
+            let score = -self.negamax(game, depth.saturating_sub(1), -i32::MAX, -alpha);
+            game.undo();
+            if best.is_none() || score > alpha {
+                alpha = score;
+                best = Some((column, score));
+            }
+        }
+        best
+    }
+
+    fn negamax(&mut self, game: &mut Game, depth: u32, mut alpha: i32, mut beta: i32) -> i32 {
+        if let Some(winner) = game.winner() {
+            // Sooner wins score higher for the winner.
+            let score = WIN + depth as i32;
+            return if winner == game.turn() { score } else { -score };
+        }
+        let moves = move_order(game);
+        if moves.is_empty() {
+            return 0;
+        }
+        if depth == 0 {
+            return evaluate(game, self.tactics_only);
+        }
+        if self
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            // The caller throws this round away, so the score is irrelevant.
+            self.timed_out = true;
+            return 0;
+        }
+        // Only entries of the same depth are used, so that scores, which
+        // count the depth of wins, come out as without the table.
+        if let Some(entry) = self.table.get(&game.hash()).filter(|e| e.depth == depth) {
+            match entry.bound {
+                Bound::Exact => return entry.score,
+                Bound::Lower => alpha = alpha.max(entry.score),
+                Bound::Upper => beta = beta.min(entry.score),
+            }
+            if alpha >= beta {
+                return entry.score;
+            }
+        }
+        let window = alpha;
+        let mut best = -i32::MAX;
+        for column in moves {
+            if game.drop(column).is_err() {
+                continue;
+            }
+            let score = -self.negamax(game, depth - 1, -beta, -alpha);
+            game.undo();
+            best = best.max(score);
+            alpha = alpha.max(score);
+            if alpha >= beta || self.timed_out {
+                break;
+            }
+        }
+        if !self.timed_out {
+            let bound = if best <= window {
+                Bound::Upper
+            } else if best >= beta {
+                Bound::Lower
+            } else {
+                Bound::Exact
+            };
+            let entry = Entry {
+                depth,
+                score: best,
+                bound,
+            };
+            self.table.insert(game.hash(), entry);
+        }
+        best
+    }
+}
+
+/// Legal lanes, center first, so strong moves are searched early. Without
+/// gravity that is the fields nearest the middle of the board.
+fn move_order(game: &Game) -> Vec<usize> {
+    let mut lanes: Vec<usize> = game.legal_lanes().collect();
+    if game.rules().gravity.is_free() {
+        let (height, width) = (game.height(), game.width());
+        lanes.sort_by_key(|&lane| {
+            let (row, column) = (lane / width, lane % width);
+            (2 * row).abs_diff(height - 1) + (2 * column).abs_diff(width - 1)
+        });
+    } else {
+        let center = game.lanes() / 2;
+        lanes.sort_by_key(|&lane| lane.abs_diff(center));
+    }
+    lanes
+}
+
+/// Static score of the position for the player to move.
+///
+/// Immediate tactics dominate: a win available now is almost as good as a
+/// win, and an opponent with two winning drops cannot be stopped. This also
+/// makes every move that hands the opponent a win on the next ply score as
+/// lost one level up. With `tactics_only` that is all there is to it.
+fn evaluate(game: &Game, tactics_only: bool) -> i32 {
+    let player = game.turn();
+    let opponent = player.other();
+    if game.rules().misere {
+        if tactics_only {
+            return 0;
+        }
+        // Lines are liabilities; the search itself sees forced fours.
+        return window_score(game, opponent) - window_score(game, player);
+    }
+    if !game.winning_drops(player).is_empty() {
+        return WIN - 1;
+    }
+    if game.winning_drops(opponent).len() > 1 {
+        return -(WIN - 1);
+    }
+    if tactics_only {
+        return 0;
+    }
+    let windows = window_score(game, player) - window_score(game, opponent);
+    windows + center_score(game, player) - center_score(game, opponent)
+}
+
+/// Sum of [`column_weights`] over the discs of `player`.
+fn center_score(game: &Game, player: Turn) -> i32 {
+    let own = Field::from(player);
+    let weights = column_weights(game.width());
+    (0..game.height())
+        .flat_map(|row| (0..game.width()).map(move |column| (row, column)))
+        .filter(|&(row, column)| game.field(row, column) == own)
+        .map(|(_, column)| weights[column])
+        .sum()
+}
+
+/// Rewards lines of `connect` fields that `player` could still complete, by
+/// how many of their discs are already in them.
+fn window_score(game: &Game, player: Turn) -> i32 {
+    let own = Field::from(player);
+    let connect = game.rules().connect;
+    let mut score = 0;
+    for row in 0..game.height() {
+        for column in 0..game.width() {
+            for (dr, dc) in [(0, 1), (1, 0), (1, 1), (1, -1)] {
+                let cells: Option<Vec<Field>> = (0..connect as isize)
+                    .map(|i| {
+                        let r = row as isize + dr * i;
+                        let c = column as isize + dc * i;
+                        if r < 0
+                            || c < 0
+                            || r as usize >= game.height()
+                            || c as usize >= game.width()
+                        {
+                            None
+                        } else {
+                            Some(game.field(r as usize, c as usize))
+                        }
+                    })
+                    .collect();
+                let Some(cells) = cells else { continue };
+                if cells
+                    .iter()
+                    .any(|&field| field != own && field != Field::Empty)
+                {
+                    continue;
+                }
+                // One disc short scores 5, two short 2, as for four in a row.
+                let missing = connect - cells.iter().filter(|&&field| field == own).count();
+                score += match missing {
+                    1 => 5,
+                    2 if connect > 2 => 2,
+                    _ => 0,
+                };
+            }
+        }
+    }
+    score
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Rules;
+
+    fn play(columns: &[usize]) -> Game {
+        let mut game = Game::new();
+        for &column in columns {
+            game.drop(column).unwrap();
+        }
+        game
+    }
+
+    #[test]
+    fn takes_immediate_win() {
+        let game = play(&[0, 6, 1, 6, 2, 5]);
+        assert_eq!(best_move(&game, 1), Some(3));
+    }
+
+    #[test]
+    fn avoids_move_that_hands_opponent_a_win() {
+        // R.....R
+        // R.....R
+        // B..R..B
+        // R..B..B
+        // B.BRB.R
+        // BBRBRBR
+        let game = play(&[
+            4, 3, 6, 0, 2, 0, 6, 5, 3, 3, 0, 0, 3, 4, 0, 6, 0, 6, 6, 2, 6, 1,
+        ]);
+        let naive = game.legal_lanes().max_by_key(|&column| {
+            let mut next = game.clone();
+            next.drop(column).unwrap();
+            window_score(&next, Turn::Red) - window_score(&next, Turn::Blue)
+        });
+        assert_eq!(naive, Some(4));
+        let mut after_naive = game.clone();
+        after_naive.drop(4).unwrap();
+        assert!(after_naive.is_winning_drop(Turn::Blue, 4));
+
+        assert_eq!(best_move(&game, 1), Some(3));
+    }
+
+    #[test]
+    fn timed_search_finds_win_and_reports_depths() {
+        let game = play(&[0, 6, 1, 6, 2, 5]);
+        let mut depths = Vec::new();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let choice = best_move_within(&game, deadline, |depth| depths.push(depth)).unwrap();
+        assert_eq!(choice.lane, 3);
+        assert_eq!(format_eval(choice.score.unwrap()), "+win");
+        assert_eq!(depths, [1]);
+    }
+
+    #[test]
+    fn easy_moves_vary_but_see_wins_and_losses() {
+        let openings: Vec<usize> = (0..7)
+            .filter_map(|seed| easy_move(&Game::new(), seed))
+            .map(|choice| choice.lane)
+            .collect();
+        assert!(openings.iter().any(|&lane| lane != openings[0]));
+        for seed in 0..7 {
+            // Red wins in column 4, and Blue has to block there.
+            assert_eq!(easy_move(&play(&[0, 6, 1, 6, 2, 5]), seed).unwrap().lane, 3);
+            assert_eq!(easy_move(&play(&[0, 6, 1, 6, 2]), seed).unwrap().lane, 3);
+        }
+    }
+
+    #[test]
+    fn expired_budget_still_returns_a_move() {
+        let choice = best_move_within(&Game::new(), Instant::now(), |_| {});
+        assert!(choice.is_some());
+    }
+
+    #[test]
+    fn misere_avoids_connecting_four() {
+        let mut game = Game::with_rules(Rules {
+            misere: true,
+            ..Rules::default()
+        });
+        for column in [0, 6, 1, 6, 2, 5] {
+            game.drop(column).unwrap();
+        }
+        assert_ne!(best_move(&game, 2), Some(3));
+    }
+
+    #[test]
+    fn blunders_are_swings_against_the_mover() {
+        assert!(is_blunder(Turn::Red, 4, -30));
+        assert!(!is_blunder(Turn::Red, -30, 4));
+        assert!(is_blunder(Turn::Blue, -4, WIN));
+        assert_eq!(format_eval(-4), "-0.4");
+    }
+
+    #[test]
+    fn eval_changes_skip_moves_without_an_evaluation() {
+        let mut game = Game::new();
+        for (column, eval) in [(3, Some(2)), (3, None), (2, Some(-25)), (4, Some(-5))] {
+            game.drop(column).unwrap();
+            if let Some(eval) = eval {
+                game.annotate_last(eval);
+            }
+        }
+        assert_eq!(
+            eval_changes(game.history()),
+            [None, None, Some((2, -25)), Some((-25, -5))]
+        );
+    }
+
+    #[test]
+    fn center_disc_scores_higher_than_edge_disc() {
+        // A single disc completes no window, so only the column weight differs.
+        let (center, edge) = (play(&[3]), play(&[0]));
+        assert_eq!(
+            window_score(&center, Turn::Red),
+            window_score(&edge, Turn::Red)
+        );
+        assert!(-evaluate(&center, false) > -evaluate(&edge, false));
+        assert_eq!(
+            evaluate(&edge, false) - evaluate(&center, false),
+            column_weights(7)[3] - column_weights(7)[0]
+        );
+        assert_eq!(column_weights(7), [0, 1, 2, 3, 2, 1, 0]);
+        assert_eq!(column_weights(8), [0, 1, 2, 3, 3, 2, 1, 0]);
+    }
+
+    #[test]
+    fn analysis_gives_score_for_red_and_best_line() {
+        let analysis = analyse(&play(&[0, 6, 1, 6, 2]), 3).unwrap();
+        // Blue has to block Red's three in column 4 first.
+        assert_eq!(analysis.line[0], 3);
+        assert!(analysis.line.len() > 1);
+        let won = analyse(&play(&[0, 6, 1, 6, 2, 5]), 3).unwrap();
+        assert_eq!(won.line, [3]);
+        assert_eq!(format_eval(won.eval), "+win");
+        assert!(analyse(&play(&[0, 6, 1, 6, 2, 5, 3]), 3).is_none());
+    }
+
+    #[test]
+    fn best_line_plays_a_forced_win_to_the_end() {
+        // Red makes an open three on the bottom row, Blue can only block
+        // one end.
+        let start = play(&[2, 2, 3, 3]);
+        let line = best_line(&start, 4);
+        assert_eq!(line.len(), 3);
+        let mut game = start.clone();
+        for &lane in &line {
+            game.drop(lane).unwrap();
+        }
+        assert_eq!(game.winner(), Some(Turn::Red));
+    }
+
+    #[test]
+    fn blocks_immediate_loss() {
+        let game = play(&[0, 6, 1, 6, 5, 6]);
+        assert_eq!(best_move(&game, 2), Some(6));
+    }
+}
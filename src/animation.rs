@@ -0,0 +1,322 @@
+//! Animations advance with a frame counter rather than the clock, so tests
+//! can step them one frame at a time.
+
+use crate::game::{Game, Gravity, MoveKind};
+
+/// Frames the winning line stays lit, then dark, when it flashes.
+pub const FLASH_FRAMES: u64 = 20;
+/// Frames until the fireworks are over, about two seconds.
+const FIREWORKS_FRAMES: u64 = 120;
+/// Frames one burst of the fireworks lasts.
+const BURST_FRAMES: u64 = 60;
+/// Where the bursts go off, as fractions of the board's width and height.
+const BURSTS: [(f64, f64); 3] = [(0.3, 0.7), (0.7, 0.6), (0.5, 0.9)];
+/// Sparks in one burst.
+const SPARKS: usize = 16;
+/// Frames the discs of a finished game take to fall off the board.
+const DRAIN_FRAMES: u64 = 30;
+
+/// How fast discs fall.
+#[derive(Copy, Clone, Default, PartialEq)]
+pub enum Speed {
+    /// Discs appear on their field at once.
+    Off,
+    Fast,
+    #[default]
+    Normal,
+    Slow,
+}
+
+impl Speed {
+    pub const ALL: [Speed; 4] = [Speed::Off, Speed::Fast, Speed::Normal, Speed::Slow];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Speed::Off => "off",
+            Speed::Fast => "fast",
+            Speed::Normal => "normal",
+            Speed::Slow => "slow",
+        }
+    }
+
+    /// Frames a falling disc needs to pass one field.
+    fn frames_per_field(self) -> u64 {
+        match self {
+            Speed::Off => 0,
+            Speed::Fast => 1,
+            Speed::Normal => 2,
+            Speed::Slow => 4,
+        }
+    }
+}
+
+/// How a win is celebrated on top of the result banner.
+#[derive(Copy, Clone, Default, PartialEq)]
+pub enum Celebration {
+    #[default]
+    Banner,
+    /// The winning line flashes.
+    Line,
+    Fireworks,
+    /// Both the flashing line and fireworks.
+    All,
+}
+
+impl Celebration {
+    pub const ALL: [Celebration; 4] = [
+        Celebration::Banner,
+        Celebration::Line,
+        Celebration::Fireworks,
+        Celebration::All,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Celebration::Banner => "banner",
+            Celebration::Line => "line",
+            Celebration::Fireworks => "fireworks",
+            Celebration::All => "all",
+        }
+    }
+
+    pub fn flashes(self) -> bool {
+        matches!(self, Celebration::Line | Celebration::All)
+    }
+
+    pub fn fireworks(self) -> bool {
+        matches!(self, Celebration::Fireworks | Celebration::All)
+    }
+}
+
+/// A disc travelling from where it entered its lane to where it landed.
+#[derive(Copy, Clone)]
+pub struct Fall {
+    from: (usize, usize),
+    to: (usize, usize),
+    start: u64,
+    frames: u64,
+}
+
+impl Fall {
+    /// The fall of the last disc dropped in `game`, starting at `frame`,
+    /// unless falls are not animated.
+    pub fn last_move(game: &Game, frame: u64, speed: Speed) -> Option<Fall> {
+        if speed == Speed::Off {
+            return None;
+        }
+        let m = game.history().last().filter(|m| m.kind != MoveKind::Pop)?;
+        let from = match game.rules().gravity {
+            Gravity::Down => (game.height() - 1, m.column),
+            Gravity::Up => (0, m.column),
+            Gravity::Left => (m.row, game.width() - 1),
+            Gravity::Right => (m.row, 0),
+            // Placed discs do not fall.
+            Gravity::Off => return None,
+        };
+        let distance = from.0.abs_diff(m.row) + from.1.abs_diff(m.column);
+        Some(Fall {
+            from,
+            to: (m.row, m.column),
+            start: frame,
+            frames: distance as u64 * speed.frames_per_field(),
+        })
+    }
+
+    /// The `(row, column)` the disc lands on.
+    pub fn target(&self) -> (usize, usize) {
+        self.to
+    }
+
+    pub fn is_done(&self, frame: u64) -> bool {
+        frame.saturating_sub(self.start) >= self.frames
+    }
+
+    /// Where the disc is at `frame` as a fractional `(row, column)`. It
+    /// speeds up on the way, like a real fall.
+    pub fn position(&self, frame: u64) -> (f64, f64) {
+        if self.is_done(frame) {
+            return (self.to.0 as f64, self.to.1 as f64);
+        }
+        let t = frame.saturating_sub(self.start) as f64 / self.frames as f64;
+        let t = t * t;
+        let between = |from: usize, to: usize| from as f64 + (to as f64 - from as f64) * t;
+        (
+            between(self.from.0, self.to.0),
+            between(self.from.1, self.to.1),
+        )
+    }
+}
+
+/// The discs of a finished game falling out of the board, in the
+/// direction of gravity, before the next game starts.
+#[derive(Clone)]
+pub struct Drain {
+    game: Game,
+    start: u64,
+}
+
+impl Drain {
+    pub fn new(game: Game, frame: u64) -> Drain {
+        Drain { game, start: frame }
+    }
+
+    /// The finished game whose discs are falling.
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    pub fn is_done(&self, frame: u64) -> bool {
+        frame.saturating_sub(self.start) >= DRAIN_FRAMES
+    }
+
+    /// How far every disc has moved at `frame`, in `(rows, columns)`. By
+    /// the end even a disc at the far edge has left the board.
+    pub fn shift(&self, frame: u64) -> (f64, f64) {
+        let t = frame.saturating_sub(self.start).min(DRAIN_FRAMES) as f64 / DRAIN_FRAMES as f64;
+        let distance = t * t * (self.game.height().max(self.game.width()) + 1) as f64;
+        match self.game.rules().gravity {
+            // Without gravity the discs fall out at the bottom.
+            Gravity::Down | Gravity::Off => (-distance, 0.0),
+            Gravity::Up => (distance, 0.0),
+            Gravity::Left => (0.0, -distance),
+            Gravity::Right => (0.0, distance),
+        }
+    }
+}
+
+/// Bursts of sparks over the board, staggered so the last one ends after
+/// [`FIREWORKS_FRAMES`].
+#[derive(Copy, Clone)]
+pub struct Fireworks {
+    start: u64,
+}
+
+impl Fireworks {
+    pub fn new(frame: u64) -> Fireworks {
+        Fireworks { start: frame }
+    }
+
+    pub fn is_done(&self, frame: u64) -> bool {
+        frame.saturating_sub(self.start) >= FIREWORKS_FRAMES
+    }
+
+    /// The sparks at `frame` on a board of `width` by `height`, as `(x, y)`
+    /// with the index of the burst they belong to. Sparks fly out, slow
+    /// down and sink.
+    pub fn sparks(&self, frame: u64, width: f64, height: f64) -> Vec<(f64, f64, usize)> {
+        let elapsed = frame.saturating_sub(self.start);
+        let stagger = (FIREWORKS_FRAMES - BURST_FRAMES) / (BURSTS.len() as u64 - 1);
+        let mut sparks = Vec::new();
+        for (burst, (x, y)) in BURSTS.into_iter().enumerate() {
+            let Some(age) = elapsed.checked_sub(burst as u64 * stagger) else {
+                continue;
+            };
+            if age >= BURST_FRAMES {
+                continue;
+            }
+            let t = age as f64 / BURST_FRAMES as f64;
+            let radius = width.min(height) * 0.25 * t.sqrt();
+            let sink = height * 0.1 * t * t;
+            for spark in 0..SPARKS {
+                let angle = spark as f64 * std::f64::consts::TAU / SPARKS as f64;
+                sparks.push((
+                    x * width + radius * angle.cos(),
+                    y * height + radius * angle.sin() - sink,
+                    burst,
+                ));
+            }
+        }
+        sparks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Rules;
+
+    #[test]
+    fn disc_falls_from_the_top_and_stops_on_its_field() {
+        let mut game = Game::new();
+        game.drop(3).unwrap();
+        let fall = Fall::last_move(&game, 100, Speed::Normal).unwrap();
+        assert_eq!(fall.position(100), (5.0, 3.0));
+        assert_eq!(fall.position(105), (3.75, 3.0));
+        assert!(!fall.is_done(109));
+        assert!(fall.is_done(110));
+        assert_eq!(fall.position(110), (0.0, 3.0));
+        assert_eq!(fall.position(500), (0.0, 3.0));
+    }
+
+    #[test]
+    fn sideways_disc_enters_from_the_opposite_edge() {
+        let mut game = Game::with_rules(Rules {
+            gravity: Gravity::Left,
+            ..Rules::default()
+        });
+        game.drop(2).unwrap();
+        let fall = Fall::last_move(&game, 0, Speed::Normal).unwrap();
+        assert_eq!(fall.target(), (2, 0));
+        assert_eq!(fall.position(0), (2.0, 6.0));
+        assert_eq!(fall.position(6), (2.0, 4.5));
+    }
+
+    #[test]
+    fn drained_discs_leave_the_board_in_the_direction_of_gravity() {
+        let mut game = Game::new();
+        game.drop(3).unwrap();
+        let drain = Drain::new(game, 10);
+        assert_eq!(drain.shift(10), (0.0, 0.0));
+        assert!(!drain.is_done(39));
+        assert!(drain.is_done(40));
+        // The top row has to fall past row 0.
+        assert!(drain.shift(40).0 + 5.0 < -1.0);
+
+        let mut game = Game::with_rules(Rules {
+            gravity: Gravity::Right,
+            ..Rules::default()
+        });
+        game.drop(0).unwrap();
+        assert!(Drain::new(game, 0).shift(40) == (0.0, 8.0));
+    }
+
+    #[test]
+    fn fireworks_burst_one_after_another_and_end() {
+        let fireworks = Fireworks::new(10);
+        let bursts = |frame| {
+            let mut bursts: Vec<usize> = fireworks
+                .sparks(frame, 350.0, 300.0)
+                .into_iter()
+                .map(|(_, _, burst)| burst)
+                .collect();
+            bursts.dedup();
+            bursts
+        };
+        assert_eq!(bursts(10), vec![0]);
+        assert_eq!(bursts(50), vec![0, 1]);
+        assert_eq!(bursts(110), vec![2]);
+        assert!(!fireworks.is_done(129));
+        assert!(fireworks.is_done(130));
+        assert!(bursts(130).is_empty());
+        let (x, y, _) = fireworks.sparks(10, 350.0, 300.0)[0];
+        assert_eq!((x, y), (105.0, 210.0));
+    }
+
+    #[test]
+    fn disc_landing_where_it_entered_does_not_move() {
+        let mut game = Game::new();
+        for _ in 0..6 {
+            game.drop(0).unwrap();
+        }
+        assert!(Fall::last_move(&game, 7, Speed::Normal).unwrap().is_done(7));
+    }
+
+    #[test]
+    fn speed_scales_the_fall() {
+        let mut game = Game::new();
+        game.drop(3).unwrap();
+        assert!(Fall::last_move(&game, 0, Speed::Fast).unwrap().is_done(5));
+        assert!(!Fall::last_move(&game, 0, Speed::Slow).unwrap().is_done(19));
+        assert!(Fall::last_move(&game, 0, Speed::Off).is_none());
+    }
+}
@@ -0,0 +1,43 @@
+//! Copying text to the system clipboard through the platform's clipboard
+//! command, so no windowing libraries are linked in.
+
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+/// Clipboard commands to try in order, with their arguments.
+const TOOLS: &[(&str, &[&str])] = &[
+    ("wl-copy", &[]),
+    ("xclip", &["-selection", "clipboard"]),
+    ("xsel", &["--clipboard", "--input"]),
+    ("pbcopy", &[]),
+    ("clip.exe", &[]),
+];
+
+/// Puts `text` on the clipboard with the first tool that works.
+pub fn copy(text: &str) -> Result<(), String> {
+    for (program, args) in TOOLS {
+        let Ok(mut child) = Command::new(program)
+            .args(*args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+        else {
+            continue;
+        };
+        let written = child
+            .stdin
+            .take()
+            .is_some_and(|mut stdin| stdin.write_all(text.as_bytes()).is_ok());
+        if child.wait().is_ok_and(|status| status.success()) && written {
+            return Ok(());
+        }
+    }
+    let names: Vec<_> = TOOLS.iter().map(|(program, _)| *program).collect();
+    Err(format!(
+        "no clipboard available (tried {})",
+        names.join(", ")
+    ))
+}
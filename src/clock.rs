@@ -0,0 +1,87 @@
+//! Chess clocks: each player has a fixed time for all of their moves.
+
+use std::time::{Duration, Instant};
+
+use connect4::game::Turn;
+
+/// The time each player has left.
+pub struct Clocks {
+    left: [Duration; 2],
+    /// When the running clock was last charged, `None` while both stand.
+    last: Option<Instant>,
+}
+
+impl Clocks {
+    pub fn new(limit: Duration) -> Clocks {
+        Clocks {
+            left: [limit; 2],
+            last: None,
+        }
+    }
+
+    /// Charges `player` for the time since the previous call, keeping their
+    /// clock running.
+    pub fn run(&mut self, player: Turn, now: Instant) {
+        if let Some(last) = self.last {
+            let left = &mut self.left[player as usize];
+            *left = left.saturating_sub(now.saturating_duration_since(last));
+        }
+        self.last = Some(now);
+    }
+
+    /// Stops both clocks until the next [`Clocks::run`].
+    pub fn stop(&mut self) {
+        self.last = None;
+    }
+
+    pub fn left(&self, player: Turn) -> Duration {
+        self.left[player as usize]
+    }
+
+    /// The player whose time ran out, if any.
+    pub fn flagged(&self) -> Option<Turn> {
+        [Turn::Red, Turn::Blue]
+            .into_iter()
+            .find(|&player| self.left(player).is_zero())
+    }
+
+    /// `player`'s time left as `m:ss`, rounded up so that `0:00` means the
+    /// time is up.
+    pub fn describe(&self, player: Turn) -> String {
+        let seconds = self.left(player).as_millis().div_ceil(1000);
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_running_clock_is_charged() {
+        let mut clocks = Clocks::new(Duration::from_secs(60));
+        let start = Instant::now();
+        clocks.run(Turn::Red, start);
+        clocks.run(Turn::Red, start + Duration::from_millis(1500));
+        clocks.stop();
+        clocks.run(Turn::Blue, start + Duration::from_secs(30));
+        clocks.run(Turn::Blue, start + Duration::from_secs(31));
+        assert_eq!(
+            (clocks.describe(Turn::Red), clocks.describe(Turn::Blue)),
+            ("0:59".to_string(), "0:59".to_string())
+        );
+        assert_eq!(clocks.left(Turn::Red), Duration::from_millis(58_500));
+        assert_eq!(clocks.flagged(), None);
+    }
+
+    #[test]
+    fn a_clock_stops_at_zero() {
+        let mut clocks = Clocks::new(Duration::from_secs(1));
+        let start = Instant::now();
+        clocks.run(Turn::Blue, start);
+        clocks.run(Turn::Blue, start + Duration::from_secs(5));
+        assert_eq!(clocks.flagged(), Some(Turn::Blue));
+        assert_eq!(clocks.describe(Turn::Blue), "0:00");
+        assert_eq!(clocks.describe(Turn::Red), "0:01");
+    }
+}
@@ -0,0 +1,353 @@
+use std::{fs, path::PathBuf, str::FromStr, time::Duration};
+
+use ratatui::symbols::Marker;
+
+use crate::{
+    ai,
+    animation::{Celebration, Speed},
+    game::{Game, Gravity, Rules, Turn},
+    geometry,
+    settings::Assist,
+    theme::Theme,
+};
+
+/// Names of the canvas markers for `--marker`.
+pub const MARKERS: [(&str, Marker); 4] = [
+    ("halfblock", Marker::HalfBlock),
+    ("braille", Marker::Braille),
+    ("dot", Marker::Dot),
+    ("block", Marker::Block),
+];
+
+/// Options chosen on the command line.
+pub struct Config {
+    pub theme: Theme,
+    pub rules: Rules,
+    /// Whether the computer plays the side `human` does not.
+    pub ai: bool,
+    /// The side the local player takes against the computer, Red (moving
+    /// first) unless chosen otherwise.
+    pub human: Turn,
+    pub difficulty: ai::Difficulty,
+    /// How long the AI may think about each move on
+    /// [`ai::Difficulty::Hard`].
+    pub ai_time: Duration,
+    /// The time each player has for all of their moves, without a limit
+    /// when `None`.
+    pub clock: Option<Duration>,
+    /// Games in the series played with rematches, won by whoever wins more
+    /// than half of them. Without one the games are only counted.
+    pub best_of: Option<u32>,
+    /// The pause after each move when the computer plays itself.
+    pub watch_delay: Duration,
+    /// Moves to play without a terminal, see [`crate::headless`].
+    pub script: Option<PathBuf>,
+    /// Read the script from standard input instead of a file.
+    pub stdin: bool,
+    /// Number of engine-against-engine games to play without a terminal.
+    pub self_play: Option<usize>,
+    /// Search depths of engines A and B in self-play.
+    pub self_play_depths: (u32, u32),
+    /// Port to wait on for a networked opponent, who plays Blue.
+    pub host: Option<u16>,
+    /// Address of a networked opponent to join as Blue.
+    pub join: Option<String>,
+    /// Whether to draw on the alternate screen, which leaves no trace in the
+    /// scrollback after quitting.
+    pub alternate_screen: bool,
+    /// Whether to draw a compact board in a few lines below the prompt
+    /// instead of taking over the screen.
+    pub inline: bool,
+    /// How the board canvas is drawn into terminal cells.
+    pub marker: Marker,
+    /// Disc size relative to the distance between fields.
+    pub disc_scale: f64,
+    /// How many of the latest moves are marked on the board.
+    pub trail: usize,
+    /// How a win is celebrated.
+    pub celebrate: Celebration,
+    pub assist: Assist,
+    /// Whether to ring the terminal bell when the opponent has moved.
+    pub bell: bool,
+    /// How fast discs fall.
+    pub animation: Speed,
+    /// Whether a move that lets the opponent win right away has to be
+    /// confirmed, outside network games.
+    pub confirm_losing: bool,
+    /// Whether the player panels are shown below the board.
+    pub panels: bool,
+    /// Whether the discs fall out of the board before a rematch.
+    pub drain: bool,
+    /// How long the game may go without input before it returns to the menu
+    /// with a fresh board, or quits with `idle_quit`.
+    pub idle_timeout: Option<Duration>,
+    pub idle_quit: bool,
+    /// Whether every field starts out labelled with its index and the frame
+    /// timing is shown.
+    pub debug: bool,
+    /// A script to review move by move in the replay screen.
+    pub replay: Option<PathBuf>,
+    /// A position to start from, see [`crate::game::Game::from_board_str`].
+    pub position: Option<PathBuf>,
+    /// A position to find the best move in, see [`crate::puzzle`].
+    pub puzzle: Option<PathBuf>,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            theme: Theme::default(),
+            rules: Rules::default(),
+            ai: false,
+            human: Turn::Red,
+            difficulty: ai::Difficulty::default(),
+            ai_time: ai::DEFAULT_TIME,
+            clock: None,
+            best_of: None,
+            watch_delay: Duration::from_millis(500),
+            script: None,
+            stdin: false,
+            self_play: None,
+            self_play_depths: (4, 4),
+            host: None,
+            join: None,
+            alternate_screen: true,
+            inline: false,
+            marker: Marker::HalfBlock,
+            disc_scale: geometry::DEFAULT_DISC_SCALE,
+            trail: 0,
+            celebrate: Celebration::default(),
+            assist: Assist::default(),
+            bell: false,
+            animation: Speed::default(),
+            drain: false,
+            panels: true,
+            confirm_losing: false,
+            idle_timeout: None,
+            idle_quit: false,
+            debug: false,
+            replay: None,
+            position: None,
+            puzzle: None,
+        }
+    }
+}
+
+impl Config {
+    /// Applies command line `args` on top of this configuration, e.g. the
+    /// saved [`crate::settings::Settings`].
+    pub fn with_args(self, args: impl IntoIterator<Item = String>) -> Result<Config, String> {
+        let mut config = self;
+        let mut ai = false;
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--theme" => {
+                    let name = value(&mut args, &arg)?;
+                    config.theme = Theme::by_name(&name).ok_or_else(|| {
+                        let names: Vec<_> = Theme::ALL.iter().map(|theme| theme.name).collect();
+                        format!(
+                            "unknown theme `{name}`, expected one of: {}",
+                            names.join(", ")
+                        )
+                    })?;
+                }
+                "--no-color" => config.theme = Theme::NO_COLOR,
+                "--gravity" => {
+                    let name = value(&mut args, &arg)?;
+                    config.rules.gravity = Gravity::ALL
+                        .into_iter()
+                        .find(|gravity| gravity.name() == name)
+                        .ok_or_else(|| {
+                            format!(
+                                "unknown gravity `{name}`, expected down, up, left, right or off"
+                            )
+                        })?;
+                }
+                "--rows" => config.rules.height = parse(&value(&mut args, &arg)?, &arg)?,
+                "--cols" => config.rules.width = parse(&value(&mut args, &arg)?, &arg)?,
+                "--max-moves" => {
+                    config.rules.max_moves = Some(parse(&value(&mut args, &arg)?, &arg)?)
+                }
+                "--connect" => config.rules.connect = parse(&value(&mut args, &arg)?, &arg)?,
+                "--misere" => config.rules.misere = true,
+                "--pop-out" => config.rules.pop_out = true,
+                "--power-pieces" => {
+                    config.rules.power_pieces = parse(&value(&mut args, &arg)?, &arg)?
+                }
+                "--ai" => ai = true,
+                "--human-color" => {
+                    let name = value(&mut args, &arg)?;
+                    config.human = Turn::by_name(&name)
+                        .ok_or_else(|| format!("unknown color `{name}`, expected red or blue"))?;
+                    ai = true;
+                }
+                "--difficulty" => {
+                    let name = value(&mut args, &arg)?;
+                    config.difficulty = ai::Difficulty::ALL
+                        .into_iter()
+                        .find(|difficulty| difficulty.name() == name)
+                        .ok_or_else(|| {
+                            format!("unknown difficulty `{name}`, expected easy, medium or hard")
+                        })?;
+                }
+                "--ai-time-ms" => {
+                    config.ai_time = Duration::from_millis(parse(&value(&mut args, &arg)?, &arg)?);
+                }
+                "--clock" => {
+                    let minutes: u64 = parse(&value(&mut args, &arg)?, &arg)?;
+                    config.clock = (minutes > 0).then(|| Duration::from_secs(60 * minutes));
+                }
+                "--best-of" => {
+                    let games = parse(&value(&mut args, &arg)?, &arg)?;
+                    if games == 0 {
+                        return Err("`--best-of` needs at least 1 game".to_string());
+                    }
+                    config.best_of = Some(games);
+                }
+                "--watch-delay-ms" => {
+                    config.watch_delay =
+                        Duration::from_millis(parse(&value(&mut args, &arg)?, &arg)?);
+                }
+                "--script" => config.script = Some(value(&mut args, &arg)?.into()),
+                "--stdin" => config.stdin = true,
+                "--self-play" => config.self_play = Some(parse(&value(&mut args, &arg)?, &arg)?),
+                "--self-play-depths" => {
+                    let depths = value(&mut args, &arg)?;
+                    config.self_play_depths = match depths.split_once(',') {
+                        Some((a, b)) => (parse(a, &arg)?, parse(b, &arg)?),
+                        None => {
+                            let depth = parse(&depths, &arg)?;
+                            (depth, depth)
+                        }
+                    };
+                }
+                "--host" => config.host = Some(parse(&value(&mut args, &arg)?, &arg)?),
+                "--join" => config.join = Some(value(&mut args, &arg)?),
+                "--no-alt-screen" => config.alternate_screen = false,
+                "--inline" => {
+                    config.inline = true;
+                    config.alternate_screen = false;
+                }
+                "--marker" => {
+                    let name = value(&mut args, &arg)?;
+                    config.marker = MARKERS
+                        .into_iter()
+                        .find(|(marker, _)| *marker == name)
+                        .map(|(_, marker)| marker)
+                        .ok_or_else(|| {
+                            format!(
+                                "unknown marker `{name}`, expected halfblock, braille, dot or block"
+                            )
+                        })?;
+                }
+                "--assist" => {
+                    let name = value(&mut args, &arg)?;
+                    config.assist = Assist::ALL
+                        .into_iter()
+                        .find(|assist| assist.name() == name)
+                        .ok_or_else(|| {
+                            format!("unknown assist `{name}`, expected off, hint or reveal")
+                        })?;
+                }
+                "--bell" => config.bell = true,
+                "--drain" => config.drain = true,
+                "--no-panels" => config.panels = false,
+                "--confirm-losing" => config.confirm_losing = true,
+                "--debug" => config.debug = true,
+                "--idle-timeout" => {
+                    let seconds = parse(&value(&mut args, &arg)?, &arg)?;
+                    if seconds == 0 {
+                        return Err("`--idle-timeout` must be at least 1 second".to_string());
+                    }
+                    config.idle_timeout = Some(Duration::from_secs(seconds));
+                }
+                "--idle-quit" => config.idle_quit = true,
+                "--animation" => {
+                    let name = value(&mut args, &arg)?;
+                    config.animation = Speed::ALL
+                        .into_iter()
+                        .find(|speed| speed.name() == name)
+                        .ok_or_else(|| {
+                            format!(
+                                "unknown animation speed `{name}`, expected off, fast, normal or slow"
+                            )
+                        })?;
+                }
+                "--celebrate" => {
+                    let name = value(&mut args, &arg)?;
+                    config.celebrate = Celebration::ALL
+                        .into_iter()
+                        .find(|celebration| celebration.name() == name)
+                        .ok_or_else(|| {
+                            format!(
+                                "unknown celebration `{name}`, expected banner, line, fireworks or all"
+                            )
+                        })?;
+                }
+                "--disc-scale" => {
+                    config.disc_scale = parse(&value(&mut args, &arg)?, &arg)?;
+                    if !(0.1..=1.0).contains(&config.disc_scale) {
+                        return Err(format!(
+                            "`--disc-scale` must be between 0.1 and 1, not {}",
+                            config.disc_scale
+                        ));
+                    }
+                }
+                "--trail" => config.trail = parse(&value(&mut args, &arg)?, &arg)?,
+                "--position" => config.position = Some(value(&mut args, &arg)?.into()),
+                "--replay" => config.replay = Some(value(&mut args, &arg)?.into()),
+                "--puzzle" => config.puzzle = Some(value(&mut args, &arg)?.into()),
+                _ => return Err(format!("unknown argument `{arg}`")),
+            }
+        }
+        config.rules.validate()?;
+        if config.stdin && config.script.is_some() {
+            return Err("`--stdin` and `--script` cannot be combined".to_string());
+        }
+        if config.host.is_some() && config.join.is_some() {
+            return Err("`--host` and `--join` cannot be combined".to_string());
+        }
+        if config.idle_quit && config.idle_timeout.is_none() {
+            return Err("`--idle-quit` needs `--idle-timeout`".to_string());
+        }
+        let networked = config.host.is_some() || config.join.is_some();
+        if config.puzzle.is_some() && (networked || config.position.is_some()) {
+            return Err(
+                "`--puzzle` cannot be combined with `--position` or a network game".to_string(),
+            );
+        }
+        if (config.rules.pop_out || config.rules.power_pieces > 0) && networked {
+            return Err(
+                "`--pop-out` and `--power-pieces` cannot be used in a network game".to_string(),
+            );
+        }
+        if ai && networked {
+            return Err("`--ai` and `--human-color` cannot be used in a network game".to_string());
+        }
+        // A saved computer opponent does not get in the way of network games.
+        config.ai = (config.ai || ai) && !networked;
+        Ok(config)
+    }
+
+    /// The game to start with: the `--position` file, or an empty board.
+    pub fn start_game(&self) -> Result<Game, String> {
+        let Some(path) = &self.position else {
+            return Ok(Game::with_rules(self.rules));
+        };
+        let text = fs::read_to_string(path)
+            .map_err(|err| format!("cannot read {}: {err}", path.display()))?;
+        Game::from_board_str(&text, self.rules).map_err(|err| format!("{}: {err}", path.display()))
+    }
+}
+
+fn value(args: &mut impl Iterator<Item = String>, flag: &str) -> Result<String, String> {
+    args.next()
+        .ok_or_else(|| format!("missing value for `{flag}`"))
+}
+
+fn parse<T: FromStr>(value: &str, flag: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid value `{value}` for `{flag}`"))
+}
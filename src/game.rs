@@ -0,0 +1,1938 @@
+use std::{error, fmt, io};
+
+/// Default board size and line length.
+pub const WIDTH: usize = 7;
+pub const HEIGHT: usize = 6;
+pub const CONNECT: usize = 4;
+/// Largest supported number of rows or columns; bigger boards do not fit
+/// on a terminal.
+pub const MAX_SIZE: usize = 20;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Turn {
+    Red,
+    Blue,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Field {
+    Empty,
+    Red,
+    Blue,
+    /// Unplayable; discs stack on it and it never counts toward a line.
+    Blocked,
+}
+
+impl Turn {
+    pub fn name(self) -> &'static str {
+        match self {
+            Turn::Red => "Red",
+            Turn::Blue => "Blue",
+        }
+    }
+
+    /// The player named `name`, in any case, e.g. `red` for `--human-color`.
+    pub fn by_name(name: &str) -> Option<Turn> {
+        [Turn::Red, Turn::Blue]
+            .into_iter()
+            .find(|turn| turn.name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn other(self) -> Turn {
+        match self {
+            Turn::Red => Turn::Blue,
+            Turn::Blue => Turn::Red,
+        }
+    }
+}
+
+/// The player's disc as written in positions, `R` or `B`.
+impl fmt::Display for Turn {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Field::from(*self).fmt(f)
+    }
+}
+
+impl From<Turn> for Field {
+    fn from(turn: Turn) -> Field {
+        match turn {
+            Turn::Red => Field::Red,
+            Turn::Blue => Field::Blue,
+        }
+    }
+}
+
+/// How a game ended other than on the board.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Ending {
+    /// The player gave up, so the opponent wins.
+    Resigned(Turn),
+    /// The player's clock ran out, so the opponent wins.
+    OutOfTime(Turn),
+    /// Stopped without a result.
+    Aborted,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum MoveError {
+    InvalidInput,
+    OutOfRange,
+    ColumnFull,
+    /// The chosen field is taken, without gravity.
+    FieldTaken,
+    /// Pop Out only: the bottom field of the column is not the mover's.
+    NothingToPop,
+    /// The mover has used up the power pieces of this kind.
+    NoPiecesLeft,
+    GameOver,
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            MoveError::InvalidInput => "not a column number",
+            MoveError::OutOfRange => "no such column",
+            MoveError::ColumnFull => "column is full",
+            MoveError::FieldTaken => "field is taken",
+            MoveError::NothingToPop => "no disc of yours at the bottom of the column",
+            MoveError::NoPiecesLeft => "none of these pieces left",
+            MoveError::GameOver => "game is over",
+        })
+    }
+}
+
+/// Why a position, settings file or other saved data could not be written
+/// or read back.
+#[derive(Debug)]
+pub enum PersistenceError {
+    Io(io::Error),
+    /// The text is not in the expected format.
+    Parse(String),
+    /// Written by a version of the game that used a different format.
+    VersionMismatch {
+        found: String,
+        expected: u32,
+    },
+    /// A board of `found` rows and columns where `expected` was needed.
+    DimensionMismatch {
+        expected: (usize, usize),
+        found: (usize, usize),
+    },
+}
+
+impl fmt::Display for PersistenceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PersistenceError::Io(err) => err.fmt(f),
+            PersistenceError::Parse(problem) => f.write_str(problem),
+            PersistenceError::VersionMismatch { found, expected } => {
+                write!(
+                    f,
+                    "written in format version {found}, but this release reads version {expected}"
+                )
+            }
+            PersistenceError::DimensionMismatch { expected, found } => write!(
+                f,
+                "found a {}x{} board, expected {}x{} (rows x columns)",
+                found.0, found.1, expected.0, expected.1
+            ),
+        }
+    }
+}
+
+impl error::Error for PersistenceError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            PersistenceError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for PersistenceError {
+    fn from(err: io::Error) -> PersistenceError {
+        PersistenceError::Io(err)
+    }
+}
+
+/// The field as written in positions, see [`Game::from_board_str`].
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_char())
+    }
+}
+
+impl Field {
+    pub fn to_char(self) -> char {
+        match self {
+            Field::Empty => '.',
+            Field::Red => 'R',
+            Field::Blue => 'B',
+            Field::Blocked => '#',
+        }
+    }
+
+    pub fn from_char(c: char) -> Option<Field> {
+        match c {
+            '.' => Some(Field::Empty),
+            'R' => Some(Field::Red),
+            'B' => Some(Field::Blue),
+            '#' => Some(Field::Blocked),
+            _ => None,
+        }
+    }
+}
+
+/// The direction discs fall in.
+#[derive(Copy, Clone, Default, PartialEq)]
+pub enum Gravity {
+    #[default]
+    Down,
+    Up,
+    Left,
+    Right,
+    /// Discs stay on whichever empty field they are placed on, as in
+    /// Gomoku. Each field is then a lane of its own.
+    Off,
+}
+
+impl Gravity {
+    pub const ALL: [Gravity; 5] = [
+        Gravity::Down,
+        Gravity::Up,
+        Gravity::Left,
+        Gravity::Right,
+        Gravity::Off,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Gravity::Down => "down",
+            Gravity::Up => "up",
+            Gravity::Left => "left",
+            Gravity::Right => "right",
+            Gravity::Off => "off",
+        }
+    }
+
+    /// Whether discs are placed on any empty field instead of dropped.
+    pub fn is_free(self) -> bool {
+        self == Gravity::Off
+    }
+
+    /// Whether players pick a row to slide discs along instead of a column.
+    pub fn is_sideways(self) -> bool {
+        matches!(self, Gravity::Left | Gravity::Right)
+    }
+
+    /// What a lane is called in the UI.
+    pub fn lane_name(self) -> &'static str {
+        match self {
+            Gravity::Left | Gravity::Right => "row",
+            Gravity::Down | Gravity::Up => "column",
+            Gravity::Off => "field",
+        }
+    }
+}
+
+/// Variant settings that stay fixed for the whole game.
+#[derive(Copy, Clone, PartialEq)]
+pub struct Rules {
+    pub width: usize,
+    pub height: usize,
+    /// How many discs in a line win.
+    pub connect: usize,
+    pub gravity: Gravity,
+    /// Connecting four loses instead of winning.
+    pub misere: bool,
+    /// Moves after which an undecided game is drawn, even with room left.
+    pub max_moves: Option<usize>,
+    /// Players may also take one of their own discs out of the bottom row,
+    /// see [`Game::pop`].
+    pub pop_out: bool,
+    /// How many bombs and how many anvils each player may drop, see
+    /// [`Game::bomb`] and [`Game::anvil`].
+    pub power_pieces: usize,
+}
+
+impl Default for Rules {
+    fn default() -> Rules {
+        Rules {
+            width: WIDTH,
+            height: HEIGHT,
+            connect: CONNECT,
+            gravity: Gravity::default(),
+            misere: false,
+            max_moves: None,
+            pop_out: false,
+            power_pieces: 0,
+        }
+    }
+}
+
+impl Rules {
+    /// The lane a disc on `(row, column)` was dropped into. Without gravity
+    /// the fields are numbered row by row from the bottom left.
+    pub fn lane(&self, row: usize, column: usize) -> usize {
+        match self.gravity {
+            Gravity::Left | Gravity::Right => row,
+            Gravity::Down | Gravity::Up => column,
+            Gravity::Off => row * self.width + column,
+        }
+    }
+
+    /// Checks that the board fits on screen and a line of `connect` fits on
+    /// the board.
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, size) in [("rows", self.height), ("columns", self.width)] {
+            if !(1..=MAX_SIZE).contains(&size) {
+                return Err(format!(
+                    "the board needs 1 to {MAX_SIZE} {name}, not {size}"
+                ));
+            }
+        }
+        if self.connect < 2 {
+            return Err(format!(
+                "a line needs at least 2 discs, not {}",
+                self.connect
+            ));
+        }
+        if self.max_moves == Some(0) {
+            return Err("the move limit must be at least 1".to_string());
+        }
+        if (self.pop_out || self.power_pieces > 0) && self.gravity != Gravity::Down {
+            return Err("Pop Out and power pieces need discs falling down".to_string());
+        }
+        if self.connect > self.width.max(self.height) {
+            return Err(format!(
+                "no line of {} fits on a board with {} rows and {} columns",
+                self.connect, self.height, self.width
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct Move {
+    pub player: Turn,
+    pub row: usize,
+    pub column: usize,
+    /// Engine evaluation after the move, from Red's side, when the engine
+    /// chose it.
+    pub eval: Option<i32>,
+    pub kind: MoveKind,
+}
+
+/// How a move changes the board. Except for a pop the mover's disc ends up
+/// on the `(row, column)` of the [`Move`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum MoveKind {
+    Drop,
+    /// Pop Out: the mover's disc taken out of the bottom of the column.
+    Pop,
+    /// A power piece that clears the top field of the column and lands there.
+    Bomb,
+    /// A power piece that clears the whole column and lands at the bottom.
+    Anvil,
+}
+
+impl MoveKind {
+    pub const POWER_PIECES: [MoveKind; 2] = [MoveKind::Bomb, MoveKind::Anvil];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            MoveKind::Drop => "disc",
+            MoveKind::Pop => "pop",
+            MoveKind::Bomb => "bomb",
+            MoveKind::Anvil => "anvil",
+        }
+    }
+
+    /// The letter written before the column of such a move, as in `p4`.
+    /// Plain drops have none.
+    pub fn prefix(self) -> Option<char> {
+        match self {
+            MoveKind::Drop => None,
+            MoveKind::Pop => Some('p'),
+            MoveKind::Bomb => Some('b'),
+            MoveKind::Anvil => Some('a'),
+        }
+    }
+
+    /// Splits a typed move such as `b4` into its kind and the column.
+    pub fn split(text: &str) -> (MoveKind, &str) {
+        [MoveKind::Pop, MoveKind::Bomb, MoveKind::Anvil]
+            .into_iter()
+            .find_map(|kind| Some((kind, text.strip_prefix(kind.prefix()?)?)))
+            .unwrap_or((MoveKind::Drop, text))
+    }
+}
+
+/// Row and column steps along the four directions a line can run in.
+const DIRECTIONS: [(isize, isize); 4] = [(0, 1), (1, 0), (1, 1), (1, -1)];
+
+/// Board and rules, independent of any rendering.
+///
+/// Row 0 is the bottom of the board. Players pick a lane to drop into: a
+/// column, or a row when gravity is sideways. Discs enter the lane from the
+/// side opposite to gravity and travel until they hit a disc or the edge.
+#[derive(Clone)]
+pub struct Game {
+    /// Fields by row, then column.
+    board: Vec<Vec<Field>>,
+    rules: Rules,
+    turn: Turn,
+    history: Vec<Move>,
+    winner: Option<Turn>,
+    ending: Option<Ending>,
+    /// The board and turn before the first move in `history`.
+    start: (Vec<Vec<Field>>, Turn),
+    /// See [`Game::hash`], kept up to date on every move.
+    hash: u64,
+}
+
+/// Mixes `value` into a well spread 64-bit number (SplitMix64).
+fn mix(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// The Zobrist key of `field` on `(row, column)`; empty fields add nothing.
+fn field_key(row: usize, column: usize, field: Field) -> u64 {
+    let kind = match field {
+        Field::Empty => return 0,
+        Field::Red => 0,
+        Field::Blue => 1,
+        Field::Blocked => 2,
+    };
+    mix(((row * MAX_SIZE + column) * 3 + kind) as u64)
+}
+
+/// Added to the hash while Blue is to move.
+fn blue_key() -> u64 {
+    mix(u64::MAX)
+}
+
+impl Default for Game {
+    fn default() -> Game {
+        Game::new()
+    }
+}
+
+impl Game {
+    pub fn new() -> Game {
+        Game::with_rules(Rules::default())
+    }
+
+    pub fn with_rules(rules: Rules) -> Game {
+        let board = vec![vec![Field::Empty; rules.width]; rules.height];
+        Game {
+            board: board.clone(),
+            rules,
+            turn: Turn::Red,
+            history: Vec::new(),
+            winner: None,
+            ending: None,
+            start: (board, Turn::Red),
+            hash: 0,
+        }
+    }
+
+    /// Reads a position written like [`Game::to_ascii`]: one line per row, top
+    /// row first, using `.`, `R`, `B` and `#` for blocked fields. The player to
+    /// move follows from the disc counts, Red moving first. A position with a
+    /// completed line is already decided.
+    pub fn from_board_str(text: &str, rules: Rules) -> Result<Game, PersistenceError> {
+        let lines: Vec<&str> = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect();
+        let (width, height) = (rules.width, rules.height);
+        let mismatch = |found| PersistenceError::DimensionMismatch {
+            expected: (height, width),
+            found,
+        };
+        if lines.len() != height {
+            let found_width = lines.first().map_or(0, |line| line.chars().count());
+            return Err(mismatch((lines.len(), found_width)));
+        }
+        let mut game = Game::with_rules(rules);
+        for (index, line) in lines.iter().enumerate() {
+            let fields: Vec<Field> = line
+                .chars()
+                .map(|c| {
+                    Field::from_char(c)
+                        .ok_or_else(|| PersistenceError::Parse(format!("unknown field `{c}`")))
+                })
+                .collect::<Result<_, _>>()?;
+            if fields.len() != width {
+                return Err(mismatch((height, fields.len())));
+            }
+            game.board[height - 1 - index] = fields;
+        }
+        let (red, blue) = (game.disc_count(Turn::Red), game.disc_count(Turn::Blue));
+        game.turn = match red.checked_sub(blue) {
+            Some(0) => Turn::Red,
+            Some(1) => Turn::Blue,
+            _ => {
+                return Err(PersistenceError::Parse(format!(
+                    "{red} red and {blue} blue discs cannot occur in a game"
+                )))
+            }
+        };
+        let lines = (game.has_line(Turn::Red), game.has_line(Turn::Blue));
+        let completed = match lines {
+            (true, true) => {
+                return Err(PersistenceError::Parse(
+                    "both players have completed a line".to_string(),
+                ))
+            }
+            (true, false) => Some(Turn::Red),
+            (false, true) => Some(Turn::Blue),
+            (false, false) => None,
+        };
+        game.winner = completed.map(|player| {
+            if game.rules.misere {
+                player.other()
+            } else {
+                player
+            }
+        });
+        game.start = (game.board.clone(), game.turn);
+        game.hash = game.full_hash();
+        Ok(game)
+    }
+
+    /// An empty board with the same rules for the next game: the loser moves
+    /// first, and after a draw the player who did not start this game does.
+    pub fn rematch(&self) -> Game {
+        let mut game = Game::with_rules(self.rules);
+        game.turn = match self.winner {
+            Some(winner) => winner.other(),
+            None => self.start.1.other(),
+        };
+        game.start.1 = game.turn;
+        game.hash = game.full_hash();
+        game
+    }
+
+    /// Whether the game has a winner or no disc can be dropped, or in Pop
+    /// Out popped, any more.
+    pub fn is_over(&self) -> bool {
+        self.winner.is_some()
+            || self.ending.is_some()
+            || (self.is_full()
+                && self.legal_pops().next().is_none()
+                && MoveKind::POWER_PIECES
+                    .iter()
+                    .all(|&kind| self.pieces_left(self.turn, kind) == 0))
+            || self.move_limit_reached()
+    }
+
+    /// Ends an undecided game off the board.
+    pub fn end(&mut self, ending: Ending) {
+        if self.is_over() {
+            return;
+        }
+        if let Ending::Resigned(player) | Ending::OutOfTime(player) = ending {
+            self.winner = Some(player.other());
+        }
+        self.ending = Some(ending);
+    }
+
+    pub fn ending(&self) -> Option<Ending> {
+        self.ending
+    }
+
+    /// Whether the game was stopped by [`Rules::max_moves`].
+    pub fn move_limit_reached(&self) -> bool {
+        self.moves_left() == Some(0)
+    }
+
+    /// Moves until [`Rules::max_moves`] ends the game, if there is a limit.
+    pub fn moves_left(&self) -> Option<usize> {
+        self.rules
+            .max_moves
+            .map(|max| max.saturating_sub(self.history.len()))
+    }
+
+    /// The position after the first `moves` moves of this game.
+    pub fn at_move(&self, moves: usize) -> Game {
+        let mut game = Game::with_rules(self.rules);
+        (game.board, game.turn) = self.start.clone();
+        game.start = self.start.clone();
+        game.hash = game.full_hash();
+        for m in &self.history[..moves.min(self.history.len())] {
+            let _ = game.play(m.kind, self.rules.lane(m.row, m.column));
+        }
+        if moves >= self.history.len() {
+            game.winner = self.winner;
+            game.ending = self.ending;
+        }
+        game
+    }
+
+    pub fn rules(&self) -> Rules {
+        self.rules
+    }
+
+    /// A Zobrist hash of the fields and the player to move, the same however
+    /// the position was reached. Only positions under the same rules are
+    /// told apart.
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// [`Game::hash`] computed from scratch.
+    fn full_hash(&self) -> u64 {
+        let mut hash = if self.turn == Turn::Blue {
+            blue_key()
+        } else {
+            0
+        };
+        for (row, fields) in self.board.iter().enumerate() {
+            for (column, &field) in fields.iter().enumerate() {
+                hash ^= field_key(row, column, field);
+            }
+        }
+        hash
+    }
+
+    pub fn width(&self) -> usize {
+        self.rules.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.rules.height
+    }
+
+    pub fn turn(&self) -> Turn {
+        self.turn
+    }
+
+    pub fn field(&self, row: usize, column: usize) -> Field {
+        self.board[row][column]
+    }
+
+    pub fn history(&self) -> &[Move] {
+        &self.history
+    }
+
+    /// The 0-based index of the first move where this game leaves the moves
+    /// of `line`, or `None` while it still follows them.
+    pub fn deviation(&self, line: &Game) -> Option<usize> {
+        self.history.iter().enumerate().position(|(index, m)| {
+            line.history
+                .get(index)
+                .is_none_or(|other| (other.row, other.column) != (m.row, m.column))
+        })
+    }
+
+    pub fn winner(&self) -> Option<Turn> {
+        self.winner
+    }
+
+    /// A short description of how the game ended so far.
+    pub fn result_text(&self) -> String {
+        match (self.ending, self.winner) {
+            (Some(Ending::Resigned(player)), _) => format!("{} resigned", player.name()),
+            (Some(Ending::OutOfTime(player)), _) => {
+                format!("{} ran out of time", player.name())
+            }
+            (Some(Ending::Aborted), _) => "Game aborted".to_string(),
+            (None, Some(winner)) if self.rules.misere => {
+                format!(
+                    "{} wins - {} connected four",
+                    winner.name(),
+                    winner.other().name()
+                )
+            }
+            (None, Some(winner)) => format!("{} wins", winner.name()),
+            (None, None) if self.is_full() => "Draw".to_string(),
+            (None, None) if self.move_limit_reached() => "Draw by move limit".to_string(),
+            (None, None) => "Unfinished".to_string(),
+        }
+    }
+
+    /// Number of lanes players can drop into.
+    pub fn lanes(&self) -> usize {
+        match self.rules.gravity {
+            Gravity::Left | Gravity::Right => self.height(),
+            Gravity::Down | Gravity::Up => self.width(),
+            Gravity::Off => self.height() * self.width(),
+        }
+    }
+
+    /// `lane` as players read it, e.g. `column 4`, or `row 2, column 5`
+    /// without gravity.
+    pub fn lane_label(&self, lane: usize) -> String {
+        match self.rules.gravity {
+            Gravity::Off => format!(
+                "row {}, column {}",
+                lane / self.width() + 1,
+                lane % self.width() + 1
+            ),
+            gravity => format!("{} {}", gravity.lane_name(), lane + 1),
+        }
+    }
+
+    /// Turns a 1-based lane typed by a player into a 0-based lane. A number
+    /// too large for `usize` is out of range like any other missing lane.
+    pub fn parse_lane(&self, input: &str) -> Result<usize, MoveError> {
+        let input = input.trim();
+        let lane: usize = input.parse().map_err(|_| {
+            if !input.is_empty() && input.bytes().all(|byte| byte.is_ascii_digit()) {
+                MoveError::OutOfRange
+            } else {
+                MoveError::InvalidInput
+            }
+        })?;
+        if lane == 0 || lane > self.lanes() {
+            return Err(MoveError::OutOfRange);
+        }
+        Ok(lane - 1)
+    }
+
+    /// The `(row, column)` a disc dropped into `lane` would land on, if it fits.
+    pub fn landing(&self, lane: usize) -> Option<(usize, usize)> {
+        if lane >= self.lanes() {
+            return None;
+        }
+        let (height, width) = (self.height() as isize, self.width() as isize);
+        let lane = lane as isize;
+        let ((mut row, mut column), (dr, dc)) = match self.rules.gravity {
+            Gravity::Down => ((height - 1, lane), (-1, 0)),
+            Gravity::Up => ((0, lane), (1, 0)),
+            Gravity::Left => ((lane, width - 1), (0, -1)),
+            Gravity::Right => ((lane, 0), (0, 1)),
+            Gravity::Off => {
+                let (row, column) = (lane as usize / self.width(), lane as usize % self.width());
+                return (self.board[row][column] == Field::Empty).then_some((row, column));
+            }
+        };
+        let empty = |row: isize, column: isize| {
+            (0..height).contains(&row)
+                && (0..width).contains(&column)
+                && self.board[row as usize][column as usize] == Field::Empty
+        };
+        if !empty(row, column) {
+            return None;
+        }
+        while empty(row + dr, column + dc) {
+            row += dr;
+            column += dc;
+        }
+        Some((row as usize, column as usize))
+    }
+
+    /// Number of occupied fields in `column`, counting blocked ones.
+    pub fn column_height(&self, column: usize) -> usize {
+        (0..self.height())
+            .filter(|&row| self.board[row][column] != Field::Empty)
+            .count()
+    }
+
+    pub fn can_play(&self, lane: usize) -> bool {
+        self.winner.is_none()
+            && self.ending.is_none()
+            && !self.move_limit_reached()
+            && self.landing(lane).is_some()
+    }
+
+    pub fn legal_lanes(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.lanes()).filter(|&lane| self.can_play(lane))
+    }
+
+    /// Whether the player to move may pop their disc out of `column`.
+    pub fn can_pop(&self, column: usize) -> bool {
+        self.rules.pop_out
+            && column < self.width()
+            && self.winner.is_none()
+            && self.ending.is_none()
+            && !self.move_limit_reached()
+            && self.board[0][column] == self.turn.into()
+    }
+
+    /// How many power pieces of `kind` `player` has not dropped yet.
+    pub fn pieces_left(&self, player: Turn, kind: MoveKind) -> usize {
+        if !MoveKind::POWER_PIECES.contains(&kind) {
+            return 0;
+        }
+        let used = self
+            .history
+            .iter()
+            .filter(|m| m.player == player && m.kind == kind)
+            .count();
+        self.rules.power_pieces.saturating_sub(used)
+    }
+
+    /// Columns the player to move may pop a disc out of, none unless
+    /// [`Rules::pop_out`].
+    pub fn legal_pops(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.width()).filter(|&column| self.can_pop(column))
+    }
+
+    /// Whether no lane has room for another disc. Blocked fields that can
+    /// never be reached do not keep the board open.
+    pub fn is_full(&self) -> bool {
+        (0..self.lanes()).all(|lane| self.landing(lane).is_none())
+    }
+
+    pub fn empty_fields(&self) -> usize {
+        self.board
+            .iter()
+            .flatten()
+            .filter(|&&field| field == Field::Empty)
+            .count()
+    }
+
+    pub fn disc_count(&self, player: Turn) -> usize {
+        let field = Field::from(player);
+        self.board.iter().flatten().filter(|&&f| f == field).count()
+    }
+
+    /// How many discs of this game were dropped into each lane.
+    pub fn lane_drops(&self) -> Vec<usize> {
+        let mut drops = vec![0; self.lanes()];
+        for m in self.history.iter().filter(|m| m.kind != MoveKind::Pop) {
+            drops[self.rules.lane(m.row, m.column)] += 1;
+        }
+        drops
+    }
+
+    /// Empty fields that would complete a line for `player`, whether or not
+    /// they can be played right now.
+    pub fn threats(&self, player: Turn) -> Vec<(usize, usize)> {
+        let mut threats = Vec::new();
+        for row in 0..self.height() {
+            for column in 0..self.width() {
+                if self.board[row][column] == Field::Empty && self.connects(row, column, player) {
+                    threats.push((row, column));
+                }
+            }
+        }
+        threats
+    }
+
+    /// How many windows of `connect` fields in a row `player` could still
+    /// fill: those without an opponent's disc or a blocked field.
+    pub fn open_lines(&self, player: Turn) -> usize {
+        let opponent = Field::from(player.other());
+        let length = self.rules.connect as isize;
+        let (height, width) = (self.height() as isize, self.width() as isize);
+        let mut open = 0;
+        for row in 0..height {
+            for column in 0..width {
+                for (dr, dc) in DIRECTIONS {
+                    let (end_row, end_column) =
+                        (row + dr * (length - 1), column + dc * (length - 1));
+                    if !(0..height).contains(&end_row) || !(0..width).contains(&end_column) {
+                        continue;
+                    }
+                    let free = (0..length).all(|k| {
+                        let field = self.board[(row + dr * k) as usize][(column + dc * k) as usize];
+                        field != opponent && field != Field::Blocked
+                    });
+                    open += usize::from(free);
+                }
+            }
+        }
+        open
+    }
+
+    /// Whether dropping into `lane` lets the opponent win with their reply,
+    /// or in misère right away by connecting the mover's own line.
+    pub fn hands_over_win(&self, lane: usize) -> bool {
+        let opponent = self.turn.other();
+        let mut after = self.clone();
+        if after.drop(lane).is_err() {
+            return false;
+        }
+        if after.is_over() {
+            return after.winner() == Some(opponent);
+        }
+        let replies: Vec<usize> = after.legal_lanes().collect();
+        replies.into_iter().any(|reply| {
+            let mut next = after.clone();
+            next.drop(reply).is_ok() && next.winner() == Some(opponent)
+        })
+    }
+
+    /// Lanes where `player` would connect four right now.
+    pub fn winning_drops(&self, player: Turn) -> Vec<usize> {
+        self.legal_lanes()
+            .filter(|&lane| self.is_winning_drop(player, lane))
+            .collect()
+    }
+
+    /// Whether `player` dropping into `lane` would connect four.
+    pub fn is_winning_drop(&self, player: Turn, lane: usize) -> bool {
+        match self.landing(lane) {
+            Some((row, column)) if self.winner.is_none() => self.connects(row, column, player),
+            _ => false,
+        }
+    }
+
+    /// Whether a disc of `player` on `(row, column)` completes a line, treating
+    /// that field as already taken by `player`.
+    fn connects(&self, row: usize, column: usize, player: Turn) -> bool {
+        let field = Field::from(player);
+        DIRECTIONS.into_iter().any(|(dr, dc)| {
+            1 + self.run(row, column, field, dr, dc) + self.run(row, column, field, -dr, -dc)
+                >= self.rules.connect
+        })
+    }
+
+    /// How many `field`s follow `(row, column)` in direction `(dr, dc)`.
+    fn run(&self, row: usize, column: usize, field: Field, dr: isize, dc: isize) -> usize {
+        let mut count = 0;
+        let (mut r, mut c) = (row as isize + dr, column as isize + dc);
+        while r >= 0
+            && c >= 0
+            && (r as usize) < self.height()
+            && (c as usize) < self.width()
+            && self.board[r as usize][c as usize] == field
+        {
+            count += 1;
+            r += dr;
+            c += dc;
+        }
+        count
+    }
+
+    /// The `(row, column)` fields of the line that decided the game, all of
+    /// them when it is longer than needed. In misère games these are the
+    /// loser's discs.
+    pub fn winning_line(&self) -> Option<Vec<(usize, usize)>> {
+        self.winner?;
+        let last = self.history.last().map(|m| (m.row, m.column));
+        let fields =
+            (0..self.height()).flat_map(|row| (0..self.width()).map(move |column| (row, column)));
+        last.into_iter()
+            .chain(fields)
+            .find_map(|(row, column)| self.line_through(row, column))
+    }
+
+    /// The longest line through `(row, column)` that is long enough to win.
+    fn line_through(&self, row: usize, column: usize) -> Option<Vec<(usize, usize)>> {
+        let field = self.board[row][column];
+        if !matches!(field, Field::Red | Field::Blue) {
+            return None;
+        }
+        DIRECTIONS
+            .into_iter()
+            .map(|(dr, dc)| {
+                let back = self.run(row, column, field, -dr, -dc) as isize;
+                let length = back + 1 + self.run(row, column, field, dr, dc) as isize;
+                (0..length)
+                    .map(|step| {
+                        let step = step - back;
+                        (
+                            (row as isize + step * dr) as usize,
+                            (column as isize + step * dc) as usize,
+                        )
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .filter(|line| line.len() >= self.rules.connect)
+            .max_by_key(Vec::len)
+    }
+
+    /// Whether some of `player`'s discs already form a line.
+    fn has_line(&self, player: Turn) -> bool {
+        (0..self.height()).any(|row| {
+            (0..self.width()).any(|column| {
+                self.board[row][column] == player.into() && self.connects(row, column, player)
+            })
+        })
+    }
+
+    /// The board as text, top row first, one character per field.
+    pub fn to_ascii(&self) -> String {
+        let mut text = String::new();
+        for row in (0..self.height()).rev() {
+            text.extend((0..self.width()).map(|column| self.board[row][column].to_char()));
+            text.push('\n');
+        }
+        text
+    }
+
+    /// A linear description of [`Game::to_ascii`] for screen readers: every
+    /// row from the top naming its occupied fields, then the turn or result.
+    pub fn describe(&self) -> String {
+        let mut text = String::new();
+        for (index, line) in self.to_ascii().lines().enumerate() {
+            let fields: Vec<String> = line
+                .chars()
+                .enumerate()
+                .filter_map(|(column, c)| {
+                    let name = match Field::from_char(c)? {
+                        Field::Empty => return None,
+                        Field::Red => "Red",
+                        Field::Blue => "Blue",
+                        Field::Blocked => "blocked",
+                    };
+                    Some(format!("{name} in column {}", column + 1))
+                })
+                .collect();
+            text += &format!(
+                "Row {}: {}. ",
+                self.height() - index,
+                if fields.is_empty() {
+                    "empty".to_string()
+                } else {
+                    fields.join(", ")
+                }
+            );
+        }
+        if self.is_over() {
+            text + &self.result_text() + "."
+        } else {
+            text + self.turn.name() + " to move."
+        }
+    }
+
+    /// Drops a disc of the current player into `lane` (0-based) and passes
+    /// the turn, returning the `(row, column)` the disc landed on.
+    pub fn drop(&mut self, lane: usize) -> Result<(usize, usize), MoveError> {
+        if lane >= self.lanes() {
+            return Err(MoveError::OutOfRange);
+        }
+        if self.winner.is_some() || self.ending.is_some() || self.move_limit_reached() {
+            return Err(MoveError::GameOver);
+        }
+        let (row, column) = self.landing(lane).ok_or(if self.rules.gravity.is_free() {
+            MoveError::FieldTaken
+        } else {
+            MoveError::ColumnFull
+        })?;
+
+        if self.connects(row, column, self.turn) {
+            self.winner = Some(if self.rules.misere {
+                self.turn.other()
+            } else {
+                self.turn
+            });
+        }
+        self.board[row][column] = self.turn.into();
+        self.hash ^= field_key(row, column, self.turn.into()) ^ blue_key();
+        self.history.push(Move {
+            player: self.turn,
+            row,
+            column,
+            eval: None,
+            kind: MoveKind::Drop,
+        });
+        self.turn = self.turn.other();
+        Ok((row, column))
+    }
+
+    /// Pop Out: takes the current player's disc out of the bottom of
+    /// `column`, lets the discs above it fall one field and passes the turn.
+    /// As this can complete lines of both players, the mover wins when it
+    /// completes both.
+    pub fn pop(&mut self, column: usize) -> Result<(), MoveError> {
+        if !self.rules.pop_out {
+            return Err(MoveError::InvalidInput);
+        }
+        if column >= self.width() {
+            return Err(MoveError::OutOfRange);
+        }
+        if self.winner.is_some() || self.ending.is_some() || self.move_limit_reached() {
+            return Err(MoveError::GameOver);
+        }
+        if !self.can_pop(column) {
+            return Err(MoveError::NothingToPop);
+        }
+        let top = self.height() - 1;
+        for row in 0..top {
+            self.board[row][column] = self.board[row + 1][column];
+        }
+        self.board[top][column] = Field::Empty;
+        let player = self.turn;
+        self.history.push(Move {
+            player,
+            row: 0,
+            column,
+            eval: None,
+            kind: MoveKind::Pop,
+        });
+        self.pass_after_change(player);
+        Ok(())
+    }
+
+    /// Drops a bomb of the current player into `column`: it clears the top
+    /// field of the column and lands there, or at the bottom of an empty
+    /// column.
+    pub fn bomb(&mut self, column: usize) -> Result<(), MoveError> {
+        self.drop_power_piece(MoveKind::Bomb, column)
+    }
+
+    /// Drops an anvil of the current player into `column`: it clears every
+    /// field of the column and lands at the bottom.
+    pub fn anvil(&mut self, column: usize) -> Result<(), MoveError> {
+        self.drop_power_piece(MoveKind::Anvil, column)
+    }
+
+    fn drop_power_piece(&mut self, kind: MoveKind, column: usize) -> Result<(), MoveError> {
+        if self.rules.power_pieces == 0 {
+            return Err(MoveError::InvalidInput);
+        }
+        if column >= self.width() {
+            return Err(MoveError::OutOfRange);
+        }
+        if self.winner.is_some() || self.ending.is_some() || self.move_limit_reached() {
+            return Err(MoveError::GameOver);
+        }
+        if self.pieces_left(self.turn, kind) == 0 {
+            return Err(MoveError::NoPiecesLeft);
+        }
+        let row = match kind {
+            MoveKind::Anvil => {
+                for row in 0..self.height() {
+                    self.board[row][column] = Field::Empty;
+                }
+                0
+            }
+            _ => match self.landing(column) {
+                Some((0, _)) => 0,
+                Some((row, _)) => row - 1,
+                None => self.height() - 1,
+            },
+        };
+        let player = self.turn;
+        self.board[row][column] = player.into();
+        self.history.push(Move {
+            player,
+            row,
+            column,
+            eval: None,
+            kind,
+        });
+        self.pass_after_change(player);
+        Ok(())
+    }
+
+    /// Passes the turn after `player` changed the board by more than adding
+    /// a disc, deciding the game on the lines found anywhere. When both
+    /// players have one, `player` made them and counts as connecting.
+    fn pass_after_change(&mut self, player: Turn) {
+        self.turn = player.other();
+        self.hash = self.full_hash();
+        let completed = match (self.has_line(player), self.has_line(player.other())) {
+            (true, _) => Some(player),
+            (false, true) => Some(player.other()),
+            (false, false) => None,
+        };
+        self.winner = completed.map(|player| {
+            if self.rules.misere {
+                player.other()
+            } else {
+                player
+            }
+        });
+    }
+
+    /// Plays a move of `kind` into `lane`, as typed or read from a script.
+    pub fn play(&mut self, kind: MoveKind, lane: usize) -> Result<(), MoveError> {
+        match kind {
+            MoveKind::Drop => self.drop(lane).map(|_| ()),
+            MoveKind::Pop => self.pop(lane),
+            MoveKind::Bomb => self.bomb(lane),
+            MoveKind::Anvil => self.anvil(lane),
+        }
+    }
+
+    /// Records the engine evaluation of the last move.
+    pub fn annotate_last(&mut self, eval: i32) {
+        if let Some(m) = self.history.last_mut() {
+            m.eval = Some(eval);
+        }
+    }
+
+    /// Takes back the last move, returning it.
+    pub fn undo(&mut self) -> Option<Move> {
+        let m = *self.history.last()?;
+        match m.kind {
+            MoveKind::Drop => {
+                self.history.pop();
+                self.board[m.row][m.column] = Field::Empty;
+                self.hash ^= field_key(m.row, m.column, m.player.into()) ^ blue_key();
+            }
+            MoveKind::Pop => {
+                self.history.pop();
+                for row in (1..self.height()).rev() {
+                    self.board[row][m.column] = self.board[row - 1][m.column];
+                }
+                self.board[0][m.column] = m.player.into();
+                self.turn = m.player;
+                self.hash = self.full_hash();
+            }
+            // The cleared fields are not kept, so the position is replayed.
+            MoveKind::Bomb | MoveKind::Anvil => {
+                let mut before = self.at_move(self.history.len() - 1);
+                for (replayed, played) in before.history.iter_mut().zip(&self.history) {
+                    replayed.eval = played.eval;
+                }
+                *self = before;
+            }
+        }
+        self.turn = m.player;
+        self.winner = None;
+        self.ending = None;
+        Some(m)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drop_reports_stacked_row() {
+        let mut game = Game::new();
+        for row in 0..HEIGHT {
+            assert_eq!(game.drop(3), Ok((row, 3)));
+        }
+    }
+
+    #[test]
+    fn at_move_rebuilds_earlier_position() {
+        let mut game = Game::new();
+        for column in [3, 3, 4] {
+            game.drop(column).unwrap();
+        }
+        assert_eq!(game.at_move(0).to_ascii(), Game::new().to_ascii());
+        let first = game.at_move(1);
+        assert_eq!(first.history().len(), 1);
+        assert_eq!(
+            (first.field(0, 3), first.field(1, 3)),
+            (Field::Red, Field::Empty)
+        );
+        assert_eq!(game.at_move(3).to_ascii(), game.to_ascii());
+    }
+
+    #[test]
+    fn detects_wins_in_every_direction() {
+        for columns in [
+            &[0, 0, 1, 1, 2, 2, 3][..],
+            &[0, 1, 0, 1, 0, 1, 0],
+            &[0, 1, 1, 2, 2, 3, 2, 3, 3, 6, 3],
+            &[6, 5, 5, 4, 4, 3, 4, 3, 3, 0, 3],
+        ] {
+            let mut game = Game::new();
+            for &column in columns {
+                assert!(game.winner().is_none());
+                game.drop(column).unwrap();
+            }
+            assert_eq!(game.winner(), Some(Turn::Red));
+            assert_eq!(game.drop(5), Err(MoveError::GameOver));
+        }
+    }
+
+    #[test]
+    fn threats_complete_a_line() {
+        let mut game = Game::new();
+        for column in [0, 6, 1, 6, 2] {
+            game.drop(column).unwrap();
+        }
+        assert_eq!(game.threats(Turn::Red), [(0, 3)]);
+        assert_eq!(game.disc_count(Turn::Red), 3);
+        assert!(game.threats(Turn::Blue).is_empty());
+    }
+
+    #[test]
+    fn misere_credits_the_other_player() {
+        let rules = Rules {
+            misere: true,
+            ..Rules::default()
+        };
+        let mut game = Game::with_rules(rules);
+        for column in [0, 1, 0, 1, 0, 1, 0] {
+            game.drop(column).unwrap();
+        }
+        assert_eq!(game.winner(), Some(Turn::Blue));
+        assert_eq!(game.result_text(), "Blue wins - Red connected four");
+
+        let mut game = Game::with_rules(rules);
+        for column in [0, 1, 0, 1, 0, 1, 6, 1] {
+            game.drop(column).unwrap();
+        }
+        assert_eq!(game.winner(), Some(Turn::Red));
+    }
+
+    #[test]
+    fn undo_restores_position() {
+        let mut game = Game::new();
+        for column in [0, 0, 1, 1, 2, 2, 3] {
+            game.drop(column).unwrap();
+        }
+        game.undo();
+        assert!(game.winner().is_none() && game.turn() == Turn::Red);
+        assert_eq!(game.to_ascii(), game.at_move(6).to_ascii());
+    }
+
+    #[test]
+    fn a_column_takes_exactly_its_height() {
+        let mut game = Game::new();
+        for row in 0..HEIGHT - 1 {
+            assert_eq!(game.drop(4), Ok((row, 4)));
+        }
+        assert_eq!(game.landing(4), Some((HEIGHT - 1, 4)));
+        assert_eq!(game.drop(4), Ok((HEIGHT - 1, 4)));
+        let turn = game.turn();
+        assert_eq!(game.landing(4), None);
+        assert_eq!(game.drop(4), Err(MoveError::ColumnFull));
+        assert_eq!((game.turn(), game.history().len()), (turn, HEIGHT));
+    }
+
+    #[test]
+    fn up_gravity_fills_columns_from_the_top() {
+        let mut game = Game::with_rules(Rules {
+            gravity: Gravity::Up,
+            ..Rules::default()
+        });
+        for row in (0..HEIGHT).rev() {
+            assert_eq!(game.drop(2), Ok((row, 2)));
+        }
+        assert_eq!(game.drop(2), Err(MoveError::ColumnFull));
+    }
+
+    #[test]
+    fn sideways_gravity_drops_along_rows() {
+        let mut game = Game::with_rules(Rules {
+            gravity: Gravity::Left,
+            ..Rules::default()
+        });
+        assert_eq!(game.lanes(), HEIGHT);
+        assert_eq!(game.drop(1), Ok((1, 0)));
+        assert_eq!(game.drop(1), Ok((1, 1)));
+        assert_eq!(game.drop(HEIGHT), Err(MoveError::OutOfRange));
+        let game = Game::with_rules(Rules {
+            gravity: Gravity::Right,
+            ..Rules::default()
+        });
+        assert_eq!(game.landing(0), Some((0, WIDTH - 1)));
+    }
+
+    #[test]
+    fn without_gravity_discs_stay_on_the_chosen_field() {
+        let mut game = Game::with_rules(Rules {
+            height: 3,
+            width: 3,
+            connect: 3,
+            gravity: Gravity::Off,
+            ..Rules::default()
+        });
+        assert_eq!(game.lanes(), 9);
+        assert_eq!(game.drop(4), Ok((1, 1)));
+        assert_eq!(game.drop(4), Err(MoveError::FieldTaken));
+        assert_eq!(game.lane_label(5), "row 2, column 3");
+        for lane in [1, 0, 3, 8] {
+            game.drop(lane).unwrap();
+        }
+        assert_eq!(game.winner(), Some(Turn::Red));
+        assert_eq!(game.winning_line(), Some(vec![(0, 0), (1, 1), (2, 2)]));
+        assert_eq!(game.lane_drops(), [1, 1, 0, 1, 1, 0, 0, 0, 1]);
+    }
+
+    #[test]
+    fn blocked_fields_support_discs_but_never_connect() {
+        let position = "\
+.......
+.......
+.......
+.......
+...#...
+###.###
+";
+        let mut game = Game::from_board_str(position, Rules::default()).unwrap();
+        assert_eq!(game.drop(0), Ok((1, 0)));
+        // Stops on the block; the field below it can never be reached.
+        assert_eq!(game.drop(3), Ok((2, 3)));
+        for column in [1, 4, 2] {
+            game.drop(column).unwrap();
+        }
+        assert!(game.winner().is_none());
+        assert_eq!(game.at_move(0).to_ascii(), position);
+    }
+
+    #[test]
+    fn full_board_with_unreachable_fields() {
+        let mut text = "#######\n".repeat(HEIGHT - 1);
+        text += "...#...";
+        let game = Game::from_board_str(&text, Rules::default()).unwrap();
+        assert!(game.is_full());
+        assert_eq!(game.legal_lanes().count(), 0);
+    }
+
+    #[test]
+    fn open_lines_leave_out_blocked_windows() {
+        let mut game = Game::new();
+        assert_eq!(game.open_lines(Turn::Red), 69);
+        game.drop(3).unwrap();
+        // Four across, one up and one on each diagonal run through (0, 3).
+        assert_eq!(game.open_lines(Turn::Red), 69);
+        assert_eq!(game.open_lines(Turn::Blue), 62);
+
+        let rules = Rules {
+            height: 1,
+            width: 5,
+            connect: 3,
+            ..Rules::default()
+        };
+        let game = Game::from_board_str(".#..R", rules).unwrap();
+        assert_eq!(game.open_lines(Turn::Red), 1);
+        assert_eq!(game.open_lines(Turn::Blue), 0);
+    }
+
+    #[test]
+    fn lane_drops_count_the_moves_per_lane() {
+        let mut game = Game::new();
+        for column in [3, 3, 0, 3] {
+            game.drop(column).unwrap();
+        }
+        assert_eq!(game.lane_drops(), [1, 0, 0, 3, 0, 0, 0]);
+
+        let mut game = Game::with_rules(Rules {
+            gravity: Gravity::Left,
+            ..Rules::default()
+        });
+        game.drop(5).unwrap();
+        assert_eq!(game.lane_drops(), [0, 0, 0, 0, 0, 1]);
+    }
+
+    #[test]
+    fn a_drop_below_the_opponents_winning_field_hands_over_the_win() {
+        let mut text = ".......\n".repeat(4);
+        text += "BBB....\nRRB.RR.\n";
+        let game = Game::from_board_str(&text, Rules::default()).unwrap();
+        assert_eq!(game.turn(), Turn::Red);
+        assert!(game.hands_over_win(3));
+        assert!(!game.hands_over_win(6));
+        assert!(!game.hands_over_win(WIDTH));
+    }
+
+    #[test]
+    fn in_misere_connecting_your_own_line_hands_over_the_win() {
+        let mut text = ".......\n".repeat(5);
+        text += "RRR.BBB\n";
+        let misere = Rules {
+            misere: true,
+            ..Rules::default()
+        };
+        let game = Game::from_board_str(&text, misere).unwrap();
+        assert_eq!(game.turn(), Turn::Red);
+        assert!(game.hands_over_win(3));
+        assert!(!game.hands_over_win(0));
+        let game = Game::from_board_str(&text, Rules::default()).unwrap();
+        assert!(!game.hands_over_win(3));
+    }
+
+    #[test]
+    fn transposed_move_orders_hash_alike() {
+        let mut first = Game::new();
+        let mut second = Game::new();
+        for column in [3, 2, 4, 2] {
+            first.drop(column).unwrap();
+        }
+        for column in [4, 2, 3, 2] {
+            second.drop(column).unwrap();
+        }
+        assert_eq!(first.to_ascii(), second.to_ascii());
+        assert_eq!(first.hash(), second.hash());
+        assert_eq!(first.hash(), first.full_hash());
+
+        second.undo();
+        second.drop(1).unwrap();
+        assert_ne!(first.hash(), second.hash());
+        second.undo();
+        second.drop(2).unwrap();
+        assert_eq!(first.hash(), second.hash());
+
+        let loaded = Game::from_board_str(&first.to_ascii(), Rules::default()).unwrap();
+        assert_eq!(loaded.hash(), first.hash());
+        let mut two = Game::new();
+        two.drop(3).unwrap();
+        two.drop(2).unwrap();
+        assert_eq!(first.at_move(2).hash(), two.hash());
+    }
+
+    #[test]
+    fn fields_and_players_display_as_in_positions() {
+        for field in [Field::Empty, Field::Red, Field::Blue, Field::Blocked] {
+            assert_eq!(Field::from_char(field.to_char()), Some(field));
+            assert_eq!(field.to_string(), field.to_char().to_string());
+        }
+        assert_eq!(format!("{} {}", Turn::Red, Turn::Blue), "R B");
+    }
+
+    #[test]
+    fn positions_of_the_wrong_size_report_both_sizes() {
+        let err = Game::from_board_str(&".......\n".repeat(5), Rules::default())
+            .err()
+            .unwrap();
+        assert!(matches!(
+            err,
+            PersistenceError::DimensionMismatch {
+                expected: (HEIGHT, WIDTH),
+                found: (5, WIDTH)
+            }
+        ));
+        let text = ".......\n".repeat(5) + "........\n";
+        let err = Game::from_board_str(&text, Rules::default()).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "found a 6x8 board, expected 6x7 (rows x columns)"
+        );
+        let text = ".......\n".repeat(5) + "...x...\n";
+        let err = Game::from_board_str(&text, Rules::default()).err().unwrap();
+        assert!(matches!(err, PersistenceError::Parse(_)));
+    }
+
+    #[test]
+    fn from_board_str_rejects_bad_positions() {
+        assert!(Game::from_board_str(".......", Rules::default()).is_err());
+        let mut text = ".......\n".repeat(HEIGHT - 1);
+        assert!(Game::from_board_str(&(text.clone() + "RR....."), Rules::default()).is_err());
+        text += "RB.x...";
+        assert!(Game::from_board_str(&text, Rules::default()).is_err());
+    }
+
+    #[test]
+    fn loaded_position_with_a_line_is_won() {
+        let mut text = ".......\n".repeat(HEIGHT - 2);
+        text += "B.B....\nRRRRB..";
+        let game = Game::from_board_str(&text, Rules::default()).unwrap();
+        assert_eq!(game.winner(), Some(Turn::Red));
+        assert!(game.is_over() && game.legal_lanes().count() == 0);
+
+        let misere = Rules {
+            misere: true,
+            ..Rules::default()
+        };
+        let game = Game::from_board_str(&text, misere).unwrap();
+        assert_eq!(game.winner(), Some(Turn::Blue));
+
+        let mut text = ".......\n".repeat(HEIGHT - 1);
+        text += "RRRRBBBB";
+        let rules = Rules {
+            width: 8,
+            ..Rules::default()
+        };
+        assert!(Game::from_board_str(&text, rules).is_err());
+    }
+
+    #[test]
+    fn winning_line_covers_every_disc_of_the_line() {
+        let mut game = Game::new();
+        assert!(game.winning_line().is_none());
+        for column in [0, 0, 1, 1, 2, 2, 4, 4] {
+            game.drop(column).unwrap();
+        }
+        assert!(game.winning_line().is_none());
+        game.drop(3).unwrap();
+        assert_eq!(
+            game.winning_line(),
+            Some(vec![(0, 0), (0, 1), (0, 2), (0, 3), (0, 4)])
+        );
+
+        let mut text = ".......\n".repeat(HEIGHT - 4);
+        text += "...R...\n..RB...\n.RBB...\nRBBR...";
+        let game = Game::from_board_str(&text, Rules::default()).unwrap();
+        assert_eq!(
+            game.winning_line(),
+            Some(vec![(0, 0), (1, 1), (2, 2), (3, 3)])
+        );
+    }
+
+    #[test]
+    fn loaded_full_board_without_a_line_is_drawn() {
+        let game = Game::from_board_str(&"RRBBRRB\nBBRRBBR\n".repeat(HEIGHT / 2), Rules::default())
+            .unwrap();
+        assert!(game.is_over() && game.winner().is_none());
+        assert_eq!(game.result_text(), "Draw");
+    }
+
+    #[test]
+    fn rematch_lets_the_loser_start() {
+        let mut game = Game::new();
+        for column in [0, 6, 0, 6, 0, 6, 0] {
+            game.drop(column).unwrap();
+        }
+        let rematch = game.rematch();
+        assert_eq!(rematch.turn(), Turn::Blue);
+        assert!(rematch.history().is_empty());
+        assert_eq!(rematch.at_move(0).turn(), Turn::Blue);
+
+        let drawn =
+            Game::from_board_str(&"RRBBRRB\nBBRRBBR\n".repeat(HEIGHT / 2), Rules::default())
+                .unwrap();
+        assert!(drawn.is_over());
+        assert_eq!(drawn.rematch().turn(), Turn::Blue);
+        assert_eq!(drawn.rematch().rematch().turn(), Turn::Red);
+    }
+
+    #[test]
+    fn describe_names_every_disc_row_by_row() {
+        let mut game = Game::new();
+        game.drop(3).unwrap();
+        game.drop(3).unwrap();
+        game.drop(0).unwrap();
+        assert_eq!(
+            game.describe(),
+            "Row 6: empty. Row 5: empty. Row 4: empty. Row 3: empty. \
+             Row 2: Blue in column 4. Row 1: Red in column 1, Red in column 4. \
+             Blue to move."
+        );
+    }
+
+    #[test]
+    fn rules_validate_board_size_and_line_length() {
+        let rules = |height, width, connect| Rules {
+            height,
+            width,
+            connect,
+            ..Rules::default()
+        };
+        assert!(Rules::default().validate().is_ok());
+        assert!(rules(3, 3, 3).validate().is_ok());
+        assert!(rules(1, 5, 5).validate().is_ok());
+        assert!(rules(3, 3, 4).validate().is_err());
+        assert!(rules(3, 3, 5).validate().is_err());
+        assert!(rules(0, 7, 4).validate().is_err());
+        assert!(rules(6, 0, 4).validate().is_err());
+        assert!(rules(MAX_SIZE, MAX_SIZE, 4).validate().is_ok());
+        assert!(rules(MAX_SIZE + 1, 7, 4).validate().is_err());
+        assert!(rules(6, 7, 1).validate().is_err());
+    }
+
+    #[test]
+    fn larger_board_with_longer_lines() {
+        let mut game = Game::with_rules(Rules {
+            height: 8,
+            width: 10,
+            connect: 5,
+            ..Rules::default()
+        });
+        for column in [0, 9, 1, 9, 2, 9, 3, 9] {
+            game.drop(column).unwrap();
+        }
+        assert!(game.winner().is_none());
+        game.drop(4).unwrap();
+        assert_eq!(game.winner(), Some(Turn::Red));
+        assert_eq!(game.to_ascii().lines().count(), 8);
+    }
+
+    #[test]
+    fn move_limit_draws_an_undecided_game() {
+        let mut game = Game::with_rules(Rules {
+            max_moves: Some(3),
+            ..Rules::default()
+        });
+        for column in [0, 1] {
+            game.drop(column).unwrap();
+        }
+        assert_eq!(game.moves_left(), Some(1));
+        assert!(!game.is_over());
+        game.drop(2).unwrap();
+        assert!(game.is_over() && game.winner().is_none() && !game.is_full());
+        assert_eq!(game.result_text(), "Draw by move limit");
+        assert_eq!(game.drop(3), Err(MoveError::GameOver));
+        assert_eq!(game.legal_lanes().count(), 0);
+        assert_eq!(Game::new().moves_left(), None);
+    }
+
+    #[test]
+    fn resigning_or_aborting_ends_the_game_with_its_own_result() {
+        let mut game = Game::new();
+        game.drop(3).unwrap();
+        game.end(Ending::Resigned(Turn::Blue));
+        assert!(game.is_over() && game.winner() == Some(Turn::Red));
+        assert_eq!(game.result_text(), "Blue resigned");
+        assert_eq!(game.drop(3), Err(MoveError::GameOver));
+        assert_eq!(game.at_move(1).result_text(), "Blue resigned");
+        assert_eq!(game.at_move(0).result_text(), "Unfinished");
+        assert_eq!(game.rematch().turn(), Turn::Blue);
+
+        let mut game = Game::new();
+        game.end(Ending::Aborted);
+        assert!(game.is_over() && game.winner().is_none());
+        assert_eq!(game.result_text(), "Game aborted");
+        game.end(Ending::Resigned(Turn::Red));
+        assert_eq!(game.ending(), Some(Ending::Aborted));
+
+        let mut game = Game::new();
+        game.end(Ending::OutOfTime(Turn::Red));
+        assert_eq!(game.winner(), Some(Turn::Blue));
+        assert_eq!(game.result_text(), "Red ran out of time");
+    }
+
+    #[test]
+    fn parse_lane_accepts_exactly_one_to_the_lane_count() {
+        for (height, width, gravity) in [
+            (HEIGHT, WIDTH, Gravity::Down),
+            (3, 10, Gravity::Down),
+            (1, 2, Gravity::Down),
+            (8, 5, Gravity::Left),
+        ] {
+            let game = Game::with_rules(Rules {
+                height,
+                width,
+                gravity,
+                ..Rules::default()
+            });
+            let lanes = game.lanes();
+            assert_eq!(lanes, if gravity.is_sideways() { height } else { width });
+            assert_eq!(game.parse_lane("0"), Err(MoveError::OutOfRange));
+            assert_eq!(game.parse_lane("1"), Ok(0));
+            assert_eq!(game.parse_lane(&lanes.to_string()), Ok(lanes - 1));
+            assert_eq!(
+                game.parse_lane(&(lanes + 1).to_string()),
+                Err(MoveError::OutOfRange)
+            );
+            assert_eq!(game.parse_lane("-1"), Err(MoveError::InvalidInput));
+        }
+    }
+
+    #[test]
+    fn a_very_long_number_is_out_of_range() {
+        let game = Game::new();
+        let digits = "9".repeat(100);
+        assert_eq!(game.parse_lane(&digits), Err(MoveError::OutOfRange));
+        assert_eq!(MoveError::OutOfRange.to_string(), "no such column");
+        assert_eq!(game.parse_lane(""), Err(MoveError::InvalidInput));
+    }
+
+    #[test]
+    fn deviation_finds_the_first_move_off_the_line() {
+        let mut line = Game::new();
+        for column in [3, 3, 2, 4] {
+            line.drop(column).unwrap();
+        }
+        let mut game = line.at_move(2);
+        assert_eq!(game.deviation(&line), None);
+        game.drop(2).unwrap();
+        assert_eq!(game.deviation(&line), None);
+        game.drop(5).unwrap();
+        assert_eq!(game.deviation(&line), Some(3));
+        game.drop(0).unwrap();
+        assert_eq!(game.deviation(&line), Some(3));
+        assert_eq!(line.deviation(&line.at_move(1)), Some(1));
+    }
+
+    #[test]
+    fn drop_rejects_out_of_range_column() {
+        let mut game = Game::new();
+        assert_eq!(game.drop(WIDTH), Err(MoveError::OutOfRange));
+    }
+
+    /// Whether `field` fills `connect` fields in a row anywhere, checking
+    /// every window without the game's own line detection.
+    fn brute_force_line(game: &Game, field: Field) -> bool {
+        let connect = game.rules().connect as isize;
+        let (height, width) = (game.height() as isize, game.width() as isize);
+        (0..height).any(|row| {
+            (0..width).any(|column| {
+                [(0, 1), (1, 0), (1, 1), (1, -1)]
+                    .into_iter()
+                    .any(|(dr, dc)| {
+                        (0..connect).all(|k| {
+                            let (r, c) = (row + dr * k, column + dc * k);
+                            (0..height).contains(&r)
+                                && (0..width).contains(&c)
+                                && game.field(r as usize, c as usize) == field
+                        })
+                    })
+            })
+        })
+    }
+
+    fn pop_out(height: usize, width: usize, connect: usize) -> Rules {
+        Rules {
+            height,
+            width,
+            connect,
+            pop_out: true,
+            ..Rules::default()
+        }
+    }
+
+    #[test]
+    fn popping_lets_the_column_fall_and_undo_puts_it_back() {
+        let mut game = Game::with_rules(pop_out(HEIGHT, WIDTH, CONNECT));
+        for lane in [3, 3, 0, 4] {
+            game.drop(lane).unwrap();
+        }
+        let before = game.clone();
+        assert_eq!(game.pop(4), Err(MoveError::NothingToPop));
+        assert_eq!(game.pop(3), Ok(()));
+        assert_eq!(
+            (game.field(0, 3), game.field(1, 3)),
+            (Field::Blue, Field::Empty)
+        );
+        assert_eq!(game.turn(), Turn::Blue);
+        assert_eq!(game.hash(), game.full_hash());
+        assert_eq!(game.at_move(5).hash(), game.hash());
+
+        game.undo();
+        assert_eq!(game.to_ascii(), before.to_ascii());
+        assert_eq!(game.hash(), before.hash());
+        assert_eq!(Game::new().pop(3), Err(MoveError::InvalidInput));
+    }
+
+    #[test]
+    fn a_pop_completing_both_players_lines_wins_for_the_mover() {
+        let mut game = Game::from_board_str("B....\nRBB.R\nBRR.R\n", pop_out(3, 5, 3)).unwrap();
+        assert_eq!(game.turn(), Turn::Blue);
+        game.pop(0).unwrap();
+        assert_eq!(game.winner(), Some(Turn::Blue));
+        assert!(game.winning_line().is_some());
+    }
+
+    #[test]
+    fn a_full_board_is_open_while_the_mover_can_pop() {
+        let mut game = Game::with_rules(pop_out(1, 2, 2));
+        game.drop(0).unwrap();
+        game.drop(1).unwrap();
+        assert!(game.is_full() && !game.is_over());
+        assert_eq!(game.legal_pops().collect::<Vec<_>>(), [0]);
+        game.pop(0).unwrap();
+        assert!(!game.is_over());
+    }
+
+    fn power_pieces(count: usize) -> Rules {
+        Rules {
+            power_pieces: count,
+            ..Rules::default()
+        }
+    }
+
+    #[test]
+    fn a_bomb_replaces_the_top_disc_and_an_anvil_clears_the_column() {
+        let mut game = Game::with_rules(power_pieces(1));
+        for lane in [3, 3, 3] {
+            game.drop(lane).unwrap();
+        }
+        game.bomb(3).unwrap();
+        assert_eq!(
+            (game.field(1, 3), game.field(2, 3), game.field(3, 3)),
+            (Field::Blue, Field::Blue, Field::Empty)
+        );
+        assert_eq!(game.bomb(0), Ok(()));
+        assert_eq!(game.field(0, 0), Field::Red);
+
+        game.anvil(3).unwrap();
+        assert_eq!(
+            (game.field(0, 3), game.field(1, 3), game.field(2, 3)),
+            (Field::Blue, Field::Empty, Field::Empty)
+        );
+        assert_eq!(game.hash(), game.full_hash());
+        assert_eq!(Game::new().bomb(3), Err(MoveError::InvalidInput));
+    }
+
+    #[test]
+    fn each_player_has_only_so_many_power_pieces() {
+        let mut game = Game::with_rules(power_pieces(1));
+        game.anvil(0).unwrap();
+        assert_eq!(game.pieces_left(Turn::Red, MoveKind::Anvil), 0);
+        assert_eq!(game.pieces_left(Turn::Blue, MoveKind::Anvil), 1);
+        game.drop(0).unwrap();
+        assert_eq!(game.anvil(1), Err(MoveError::NoPiecesLeft));
+        assert_eq!(game.bomb(1), Ok(()));
+    }
+
+    #[test]
+    fn undoing_a_power_piece_restores_the_board_and_evals() {
+        let mut game = Game::with_rules(power_pieces(2));
+        for lane in [3, 3, 4] {
+            game.drop(lane).unwrap();
+            game.annotate_last(lane as i32);
+        }
+        let before = game.clone();
+        game.anvil(3).unwrap();
+        game.undo();
+        assert_eq!(game.to_ascii(), before.to_ascii());
+        assert_eq!(game.hash(), before.hash());
+        assert_eq!(game.turn(), Turn::Blue);
+        let evals: Vec<_> = game.history().iter().map(|m| m.eval).collect();
+        assert_eq!(evals, [Some(3), Some(3), Some(4)]);
+    }
+
+    #[test]
+    fn random_inputs_keep_the_board_consistent() {
+        // A fixed xorshift sequence, so failures can be replayed.
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut next = |bound: usize| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % bound as u64) as usize
+        };
+        for round in 0..300 {
+            let gravity = Gravity::ALL[next(Gravity::ALL.len())];
+            let rules = Rules {
+                height: 1 + next(8),
+                width: 2 + next(8),
+                connect: 2 + next(3),
+                gravity,
+                misere: round % 5 == 0,
+                ..Rules::default()
+            };
+            let rules = Rules {
+                connect: rules.connect.min(rules.height.max(rules.width)),
+                ..rules
+            };
+            let mut game = Game::with_rules(rules);
+            for _ in 0..80 {
+                let input = match next(10) {
+                    0 => "x".to_string(),
+                    1 => String::new(),
+                    _ => next(game.lanes() + 3).to_string(),
+                };
+                let before = game.history().len();
+                let result = game.parse_lane(&input).and_then(|lane| game.drop(lane));
+                assert_eq!(game.history().len(), before + usize::from(result.is_ok()));
+
+                let discs = game.disc_count(Turn::Red) + game.disc_count(Turn::Blue);
+                assert_eq!(discs, game.history().len());
+                // The field a disc falls towards is taken or off the board.
+                let (dr, dc): (isize, isize) = match gravity {
+                    Gravity::Down => (-1, 0),
+                    Gravity::Up => (1, 0),
+                    Gravity::Left => (0, -1),
+                    Gravity::Right => (0, 1),
+                    // Placed discs need no support.
+                    Gravity::Off => (0, 0),
+                };
+                for row in 0..game.height() {
+                    for column in 0..game.width() {
+                        let (r, c) = (row as isize + dr, column as isize + dc);
+                        let supported = r < 0
+                            || c < 0
+                            || r >= game.height() as isize
+                            || c >= game.width() as isize
+                            || game.field(r as usize, c as usize) != Field::Empty;
+                        assert!(game.field(row, column) == Field::Empty || supported);
+                    }
+                }
+                let lines = [Turn::Red, Turn::Blue].map(|p| brute_force_line(&game, p.into()));
+                match game.winner() {
+                    Some(winner) => {
+                        let scorer = if rules.misere { winner.other() } else { winner };
+                        assert!(lines[scorer as usize]);
+                        assert!(game.is_over() && game.drop(0) == Err(MoveError::GameOver));
+                    }
+                    None => assert_eq!(lines, [false, false]),
+                }
+                if game.is_over() {
+                    game = Game::with_rules(rules);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,132 @@
+//! Where the board is drawn in canvas coordinates.
+
+/// Distance between neighbouring fields.
+pub const SPACING: f64 = 50.0;
+/// Disc size as a fraction of [`SPACING`] unless `--disc-scale` says
+/// otherwise.
+pub const DEFAULT_DISC_SCALE: f64 = 0.8;
+/// Space around the board for the arrow showing where discs enter.
+const MARGIN: f64 = 30.0;
+/// How many times taller than wide a terminal cell usually is.
+const CELL_ASPECT: f64 = 2.0;
+
+/// Field positions for discs of one size. Rows and columns are as drawn:
+/// row 0 at the bottom, display column 0 on the left. They may be
+/// fractional for discs on their way and for marks outside the board.
+#[derive(Copy, Clone)]
+pub struct Geometry {
+    /// Side of a disc.
+    pub disc: f64,
+}
+
+impl Geometry {
+    pub fn new(scale: f64) -> Geometry {
+        Geometry {
+            disc: SPACING * scale,
+        }
+    }
+
+    /// Bottom left corner of the disc on `(row, column)`.
+    pub fn corner(&self, row: f64, column: f64) -> (f64, f64) {
+        (column * SPACING, row * SPACING)
+    }
+
+    /// Where a one character label in the middle of `(row, column)` goes,
+    /// moved right by `offset` disc sizes.
+    pub fn label(&self, row: f64, column: f64, offset: f64) -> (f64, f64) {
+        let (x, y) = self.corner(row, column);
+        (x + self.disc * (0.5 + offset) - 2.0, y + self.disc / 2.0)
+    }
+
+    pub fn x_bounds(&self, width: usize) -> [f64; 2] {
+        self.bounds(width)
+    }
+
+    pub fn y_bounds(&self, height: usize) -> [f64; 2] {
+        self.bounds(height)
+    }
+
+    /// Canvas bounds for a board of `width` by `height` fields drawn into
+    /// `columns` by `rows` terminal cells. The board's bounds are widened in
+    /// one direction so that a canvas unit is as long across as up, and
+    /// discs look round however the area is shaped.
+    pub fn fitted_bounds(
+        &self,
+        width: usize,
+        height: usize,
+        columns: u16,
+        rows: u16,
+    ) -> ([f64; 2], [f64; 2]) {
+        let (x, y) = (self.x_bounds(width), self.y_bounds(height));
+        let (columns, rows) = (columns.max(1) as f64, rows.max(1) as f64 * CELL_ASPECT);
+        // Canvas units per cell width, the same both ways.
+        let unit = ((x[1] - x[0]) / columns).max((y[1] - y[0]) / rows);
+        let widen = |[low, high]: [f64; 2], cells: f64| {
+            let extra = (unit * cells - (high - low)) / 2.0;
+            [low - extra, high + extra]
+        };
+        (widen(x, columns), widen(y, rows))
+    }
+
+    fn bounds(&self, fields: usize) -> [f64; 2] {
+        let last = fields.saturating_sub(1) as f64 * SPACING;
+        [-MARGIN, last + self.disc + MARGIN]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn discs_and_entry_arrows_stay_within_bounds() {
+        for scale in [0.1, DEFAULT_DISC_SCALE, 1.0] {
+            let geometry = Geometry::new(scale);
+            for (width, height) in [(1, 1), (7, 6), (3, 10), (10, 3), (20, 20)] {
+                let [left, right] = geometry.x_bounds(width);
+                let [bottom, top] = geometry.y_bounds(height);
+                let inside =
+                    |(x, y): (f64, f64)| (left..=right).contains(&x) && (bottom..=top).contains(&y);
+                for row in 0..height {
+                    for column in 0..width {
+                        let (x, y) = geometry.corner(row as f64, column as f64);
+                        assert!(inside((x, y)) && inside((x + geometry.disc, y + geometry.disc)));
+                    }
+                }
+                let (last_row, last_column) = ((height - 1) as f64, (width - 1) as f64);
+                for (row, column) in [(-0.6, 0.0), (last_row + 0.6, 0.0), (0.0, -0.6)] {
+                    assert!(inside(geometry.label(row, column, 0.0)));
+                }
+                assert!(inside(geometry.label(0.0, last_column + 0.6, 0.0)));
+            }
+        }
+    }
+
+    #[test]
+    fn narrow_and_wide_boards_fit_with_round_discs() {
+        let geometry = Geometry::new(DEFAULT_DISC_SCALE);
+        for (width, height) in [(3, 10), (10, 3), (7, 6)] {
+            for (columns, rows) in [(80, 24), (40, 40), (120, 10)] {
+                let (x, y) = geometry.fitted_bounds(width, height, columns, rows);
+                let board_x = geometry.x_bounds(width);
+                let board_y = geometry.y_bounds(height);
+                assert!(x[0] <= board_x[0] + 1e-9 && board_x[1] <= x[1] + 1e-9);
+                assert!(y[0] <= board_y[0] + 1e-9 && board_y[1] <= y[1] + 1e-9);
+                let across = (x[1] - x[0]) / columns as f64;
+                let up = (y[1] - y[0]) / (rows as f64 * CELL_ASPECT);
+                assert!((across - up).abs() < 1e-9);
+                // The board fills the area one way or the other.
+                let filled = ((board_x[1] - board_x[0]) / (x[1] - x[0]))
+                    .max((board_y[1] - board_y[0]) / (y[1] - y[0]));
+                assert!((filled - 1.0).abs() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn default_discs_leave_a_gap() {
+        let geometry = Geometry::new(DEFAULT_DISC_SCALE);
+        assert_eq!(geometry.disc, 40.0);
+        assert_eq!(geometry.label(1.0, 2.0, 0.0), (118.0, 70.0));
+    }
+}
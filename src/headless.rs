@@ -0,0 +1,299 @@
+//! Playing a game from a list of moves, without a terminal.
+//!
+//! The process exit code tells scripts how the game went: see
+//! [`exit_code`] and the `EXIT_*` constants.
+
+use std::{
+    fmt, fs,
+    io::{self, Read},
+    path::Path,
+};
+
+use crate::{
+    ai,
+    game::{Game, MoveError, MoveKind, Turn},
+};
+
+/// A move in the script could not be played.
+pub const EXIT_ILLEGAL_MOVE: i32 = 3;
+/// The script file or standard input could not be read.
+pub const EXIT_UNREADABLE: i32 = 4;
+/// The command line arguments were invalid.
+pub const EXIT_USAGE: i32 = 64;
+/// The port of a hosted game could not be listened on.
+pub const EXIT_UNAVAILABLE: i32 = 69;
+/// Drawing to or reading from the terminal failed.
+pub const EXIT_IO: i32 = 74;
+
+/// A move of a script that could not be played.
+#[derive(Debug, PartialEq)]
+pub struct ScriptError {
+    /// 1-based position of the move in the script.
+    pub position: usize,
+    pub token: String,
+    pub error: MoveError,
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "move {} (`{}`): {}",
+            self.position, self.token, self.error
+        )
+    }
+}
+
+/// Plays `script`, whitespace separated 1-based lane numbers, starting from
+/// `game`. A `p` before the number pops instead in Pop Out, e.g. `p4`, and
+/// a `b` or `a` drops a bomb or anvil.
+pub fn run(script: &str, mut game: Game) -> Result<Game, ScriptError> {
+    for (index, token) in script.split_whitespace().enumerate() {
+        let (kind, lane) = MoveKind::split(token);
+        game.parse_lane(lane)
+            .and_then(|lane| game.play(kind, lane))
+            .map_err(|error| ScriptError {
+                position: index + 1,
+                token: token.to_string(),
+                error,
+            })?;
+    }
+    Ok(game)
+}
+
+/// Reads pasted moves as 0-based lanes of `game`: whitespace or comma
+/// separated 1-based lane numbers, or one digit per move when there are no
+/// separators, like `4435`. Only checks that each lane exists; whether the
+/// moves can be played shows when they are.
+pub fn parse_moves(text: &str, game: &Game) -> Result<Vec<usize>, ScriptError> {
+    let text = text.trim();
+    let tokens: Vec<&str> = if text.contains(|c: char| c.is_whitespace() || c == ',') {
+        text.split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|token| !token.is_empty())
+            .collect()
+    } else {
+        text.char_indices()
+            .map(|(index, c)| &text[index..index + c.len_utf8()])
+            .collect()
+    };
+    tokens
+        .into_iter()
+        .enumerate()
+        .map(|(index, token)| {
+            game.parse_lane(token).map_err(|error| ScriptError {
+                position: index + 1,
+                token: token.to_string(),
+                error,
+            })
+        })
+        .collect()
+}
+
+/// Plays the script at `path`, printing the final board, and returns the
+/// exit code for the outcome.
+pub fn play_script(path: &Path, start: Game) -> i32 {
+    let script = match fs::read_to_string(path) {
+        Ok(script) => script,
+        Err(err) => {
+            eprintln!("error: cannot read {}: {err}", path.display());
+            return EXIT_UNREADABLE;
+        }
+    };
+    play(&script, start)
+}
+
+/// Like [`play_script`], reading the moves from standard input.
+pub fn play_stdin(start: Game) -> i32 {
+    let mut script = String::new();
+    if let Err(err) = io::stdin().read_to_string(&mut script) {
+        eprintln!("error: cannot read standard input: {err}");
+        return EXIT_UNREADABLE;
+    }
+    play(&script, start)
+}
+
+fn play(script: &str, start: Game) -> i32 {
+    match run(script, start) {
+        Ok(game) => {
+            print!("{}", report(&game));
+            exit_code(&game)
+        }
+        Err(err) => {
+            eprintln!("error: {err}");
+            EXIT_ILLEGAL_MOVE
+        }
+    }
+}
+
+/// 0 for a draw or unfinished game, 1 when Red won and 2 when Blue won.
+pub fn exit_code(game: &Game) -> i32 {
+    match game.winner() {
+        None => 0,
+        Some(Turn::Red) => 1,
+        Some(Turn::Blue) => 2,
+    }
+}
+
+/// The final board followed by the result.
+pub fn report(game: &Game) -> String {
+    format!(
+        "{}{} after {} moves\n",
+        game.to_ascii(),
+        game.result_text(),
+        game.history().len()
+    )
+}
+
+/// Totals of [`self_play`] between engine A and engine B.
+#[derive(Debug, Default, PartialEq)]
+pub struct SelfPlayStats {
+    pub a_wins: usize,
+    pub b_wins: usize,
+    pub draws: usize,
+    /// Draws because of [`crate::game::Rules::max_moves`], also counted in
+    /// `draws`.
+    pub move_limit_draws: usize,
+}
+
+/// Plays `games` games from `start` between two engines searching to fixed
+/// depths, so the results are reproducible. Engine A makes the first move
+/// of even games and engine B of odd ones.
+pub fn self_play(start: &Game, games: usize, depths: (u32, u32)) -> SelfPlayStats {
+    let mut stats = SelfPlayStats::default();
+    for index in 0..games {
+        let a = if index % 2 == 0 {
+            start.turn()
+        } else {
+            start.turn().other()
+        };
+        let mut game = start.clone();
+        while !game.is_over() {
+            let depth = if game.turn() == a { depths.0 } else { depths.1 };
+            let Some(lane) = ai::best_move(&game, depth) else {
+                break;
+            };
+            if game.drop(lane).is_err() {
+                break;
+            }
+        }
+        match game.winner() {
+            Some(winner) if winner == a => stats.a_wins += 1,
+            Some(_) => stats.b_wins += 1,
+            None => {
+                stats.draws += 1;
+                if game.move_limit_reached() {
+                    stats.move_limit_draws += 1;
+                }
+            }
+        }
+    }
+    stats
+}
+
+/// The results of [`self_play`] as a table.
+pub fn self_play_report(stats: &SelfPlayStats, depths: (u32, u32)) -> String {
+    let mut report = format!(
+        "\
+engine  depth  wins
+A       {:<5}  {}
+B       {:<5}  {}
+draws          {}
+",
+        depths.0, stats.a_wins, depths.1, stats.b_wins, stats.draws
+    );
+    if stats.move_limit_draws > 0 {
+        report += &format!("  by move limit  {}\n", stats.move_limit_draws);
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Field, Rules};
+
+    #[test]
+    fn plays_script_without_terminal() {
+        let game = run("4 3\n4 3 4 3\n4", Game::new()).unwrap();
+        assert_eq!(
+            report(&game),
+            "\
+.......
+.......
+...R...
+..BR...
+..BR...
+..BR...
+Red wins after 7 moves
+"
+        );
+    }
+
+    #[test]
+    fn exit_code_encodes_winner() {
+        assert_eq!(exit_code(&run("4 3 4 3 4 3 4", Game::new()).unwrap()), 1);
+        assert_eq!(exit_code(&run("1 4 3 4 3 4 3 4", Game::new()).unwrap()), 2);
+        assert_eq!(exit_code(&run("1 2 3", Game::new()).unwrap()), 0);
+    }
+
+    #[test]
+    fn scripts_pop_with_a_p_before_the_column() {
+        let pop_out = Game::with_rules(Rules {
+            pop_out: true,
+            ..Rules::default()
+        });
+        let game = run("4 4 p4", pop_out.clone()).unwrap();
+        assert_eq!((game.field(0, 3), game.history().len()), (Field::Blue, 3));
+        let err = run("4 p4", pop_out).err().unwrap();
+        assert_eq!((err.position, err.error), (2, MoveError::NothingToPop));
+        assert_eq!(
+            run("4 p4", Game::new()).err().unwrap().error,
+            MoveError::InvalidInput
+        );
+    }
+
+    #[test]
+    fn self_play_alternates_the_first_engine() {
+        // Equal engines are deterministic, so each colour wins the same way.
+        let stats = self_play(&Game::new(), 2, (1, 1));
+        assert_eq!(stats.a_wins + stats.b_wins + stats.draws, 2);
+        assert_eq!(stats.a_wins, stats.b_wins);
+    }
+
+    #[test]
+    fn pasted_moves_with_or_without_separators() {
+        let game = Game::new();
+        assert_eq!(parse_moves("4435\n", &game), Ok(vec![3, 3, 2, 4]));
+        assert_eq!(parse_moves("4, 4 3,5", &game), Ok(vec![3, 3, 2, 4]));
+        let err = parse_moves("4485", &game).err().unwrap();
+        assert_eq!((err.position, err.error), (3, MoveError::OutOfRange));
+        assert_eq!(
+            parse_moves("44a", &game).err().unwrap().error,
+            MoveError::InvalidInput
+        );
+    }
+
+    #[test]
+    fn move_limit_draws_are_reported_apart() {
+        let start = Game::with_rules(Rules {
+            max_moves: Some(4),
+            ..Rules::default()
+        });
+        let stats = self_play(&start, 2, (1, 1));
+        assert_eq!((stats.draws, stats.move_limit_draws), (2, 2));
+        assert!(self_play_report(&stats, (1, 1)).ends_with("  by move limit  2\n"));
+        let game = run("1 2 3 4", start).unwrap();
+        assert!(report(&game).ends_with("Draw by move limit after 4 moves\n"));
+    }
+
+    #[test]
+    fn reports_first_illegal_move() {
+        let err = run("1 1 1 1 1 1 1 2", Game::new()).err().unwrap();
+        assert_eq!(err.position, 7);
+        assert_eq!(err.error, MoveError::ColumnFull);
+        assert_eq!(
+            run("4 x", Game::new()).err().unwrap().error,
+            MoveError::InvalidInput
+        );
+    }
+}
@@ -0,0 +1,6 @@
+//! The rules and the engine, without a terminal: what the game is built on
+//! and what `benches/` measures.
+
+pub mod ai;
+pub mod game;
+pub mod headless;
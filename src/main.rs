@@ -1,10 +1,14 @@
 use std::{
-    io::{self, stdout, Stdout},
+    collections::VecDeque,
+    fs,
+    io::{self, stdout, IsTerminal, Stdout, Write},
+    path::Path,
+    sync::mpsc::Receiver,
     time::{Duration, Instant},
 };
 
 use crossterm::{
-    event::{self, Event, KeyCode},
+    event::{self, DisableBracketedPaste, EnableBracketedPaste, Event, KeyCode},
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
     ExecutableCommand,
 };
@@ -13,211 +17,3255 @@ use ratatui::{
     widgets::{canvas::*, *},
 };
 
-fn main() -> io::Result<()> {
-    App::run()
+mod animation;
+#[cfg(feature = "clipboard")]
+mod clipboard;
+mod clock;
+mod config;
+mod geometry;
+mod mode;
+mod net;
+mod openings;
+mod puzzle;
+mod score;
+mod settings;
+mod theme;
+mod timing;
+mod tutorial;
+
+use config::Config;
+use connect4::{ai, game, headless};
+use game::{Ending, Field, Game, Gravity, Move, MoveError, MoveKind, Turn};
+use settings::{Assist, Settings};
+use theme::Theme;
+
+const REPLAY_FILE: &str = "connect4-replay.txt";
+/// How often the event loop ticks, advancing animations.
+const TICK_RATE: Duration = Duration::from_millis(16);
+/// Most characters in one chat message.
+const CHAT_LIMIT: usize = 200;
+/// Chat messages shown at once, including the one being typed.
+const CHAT_LINES: u16 = 3;
+/// Ticks a suggested move stays marked on the board, about 3 seconds.
+const HINT_FRAMES: u64 = 180;
+/// Moves the move list scrolls by with PageUp and PageDown.
+const MOVE_LIST_PAGE: usize = 10;
+/// Terminal columns taken by the move list next to the board.
+const MOVE_LIST_WIDTH: u16 = 24;
+/// Frames between two pasted moves, so they can be followed.
+const PASTE_DELAY: u64 = 15;
+/// Terminal columns and rows a field needs for the canvas to stay legible.
+const CANVAS_FIELD: (u16, u16) = (4, 2);
+
+fn main() {
+    let mut saved = Config::default();
+    if let Err(err) = settings::load(&mut saved) {
+        eprintln!("warning: saved settings not used: {err}");
+    }
+    let (no_color, term) = (std::env::var_os("NO_COLOR"), std::env::var_os("TERM"));
+    if !theme::color_supported(no_color.as_deref(), term.as_deref()) {
+        saved.theme = Theme::NO_COLOR;
+    }
+    let config = match saved.with_args(std::env::args().skip(1)) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("error: {err}");
+            std::process::exit(headless::EXIT_USAGE);
+        }
+    };
+    let start = match config.start_game() {
+        Ok(game) => game,
+        Err(err) => {
+            eprintln!("error: {err}");
+            std::process::exit(headless::EXIT_UNREADABLE);
+        }
+    };
+    if let Some(path) = &config.script {
+        std::process::exit(headless::play_script(path, start));
+    }
+    if let Some(games) = config.self_play {
+        let stats = headless::self_play(&start, games, config.self_play_depths);
+        print!(
+            "{}",
+            headless::self_play_report(&stats, config.self_play_depths)
+        );
+        std::process::exit(0);
+    }
+    if config.stdin {
+        std::process::exit(headless::play_stdin(start));
+    }
+    if !stdout().is_terminal() {
+        eprintln!("error: stdout is not a terminal; use `--script <file>` to play without one");
+        std::process::exit(headless::EXIT_USAGE);
+    }
+    if let Err(err) = App::run(config, start) {
+        eprintln!("error: {err}");
+        std::process::exit(headless::EXIT_IO);
+    }
 }
 
-#[derive(Copy, Clone)]
-enum Turn {
-    Red,
-    Blue,
+enum Screen {
+    Menu {
+        selected: usize,
+    },
+    Playing,
+    /// The finished game's summary over its board, with
+    /// [`GAME_OVER_ITEMS`] to go on.
+    GameOver {
+        selected: usize,
+    },
+    Tutorial {
+        step: usize,
+        game: Game,
+    },
+    /// Reviewing the current game at the position after `position` moves.
+    Replay {
+        position: usize,
+    },
+    /// Changing `settings`, which take effect on leaving the screen.
+    Settings {
+        selected: usize,
+        settings: Settings,
+    },
+    /// Picking one of [`openings::OPENINGS`] to start a game from.
+    Openings {
+        selected: usize,
+    },
 }
 
-#[derive(Copy, Clone)]
-enum Field {
-    Empty,
-    Red,
-    Blue,
-}
+/// Delays between moves in replay auto-play, from fastest to slowest.
+const REPLAY_DELAYS_MS: [u64; 7] = [100, 200, 350, 500, 1000, 1500, 2000];
+
+const MENU_ITEMS: [&str; 7] = [
+    "Play vs human",
+    "Play vs computer",
+    "AI vs AI",
+    "Tutorial",
+    "Settings",
+    "Openings",
+    "Quit",
+];
+/// Indices into [`MENU_ITEMS`], for [`App::choose`] and for coming back to
+/// the item a screen was opened from.
+const MENU_PLAY: usize = 0;
+const MENU_PLAY_COMPUTER: usize = 1;
+const MENU_EXHIBITION: usize = 2;
+const MENU_TUTORIAL: usize = 3;
+const MENU_SETTINGS: usize = 4;
+const MENU_OPENINGS: usize = 5;
+const MENU_QUIT: usize = 6;
+
+const GAME_OVER_ITEMS: [&str; 2] = ["Rematch", "Back to menu"];
+
+struct App {
+    screen: Screen,
+    quit: bool,
+    game: Game,
+    input: String,
+    theme: Theme,
+    message: Option<String>,
+    ai: Option<Turn>,
+    /// The side the local player takes when the computer plays the other.
+    human: Turn,
+    difficulty: ai::Difficulty,
+    ai_time: Duration,
+    thinking: Option<Thinking>,
+    mirrored: bool,
+    /// Whether the board is drawn turned by 180°, upside down and mirrored.
+    rotated: bool,
+    analysis: bool,
+    /// The engine's view of the position shown in analysis mode, with the
+    /// board it belongs to.
+    evaluation: Option<(PositionKey, Option<ai::Analysis>)>,
+    /// A running analysis and the board it is for.
+    analysing: Option<(PositionKey, Receiver<Option<ai::Analysis>>)>,
+    /// The search for a suggested move in a position, asked for with `h`.
+    hinting: Option<(PositionKey, Receiver<ai::SearchEvent>)>,
+    /// The suggested lane for a position, marked until the given frame.
+    hint: Option<(PositionKey, usize, u64)>,
+    assist: Assist,
+    /// Whether the computer plays both sides, see [`App::start_exhibition`].
+    exhibition: bool,
+    /// Whether the exhibition is held before the next move.
+    paused: bool,
+    /// When the last move was played, to space out exhibition moves.
+    last_move: Instant,
+    watch_delay: Duration,
+    /// When the first move of the game was played.
+    started: Option<Instant>,
+    /// How long the last finished game took, from its first move.
+    game_time: Option<Duration>,
+    /// The time each player has for a game, without clocks when `None`.
+    clock: Option<Duration>,
+    /// The clocks of the game, running from its first move.
+    clocks: Option<clock::Clocks>,
+    /// How many of the latest moves are marked, newest strongest.
+    trail: usize,
+    geometry: geometry::Geometry,
+    marker: Marker,
+    celebrate: animation::Celebration,
+    /// Whether to ring the terminal bell when the opponent has moved.
+    bell: bool,
+    animation: animation::Speed,
+    /// Fireworks for a win, until they burn out or a key is pressed.
+    fireworks: Option<animation::Fireworks>,
+    /// Whether moves that hand the opponent a win need confirming.
+    confirm_losing: bool,
+    /// The moves that solve the puzzle being played, until one is found.
+    puzzle: Option<Vec<usize>>,
+    /// The solution of the puzzle played out to the end, and how many of its
+    /// moves are shown, while stepping through it.
+    walkthrough: Option<(Game, usize)>,
+    /// The game as it was before the latest undos, to redo its moves.
+    undone: Option<Game>,
+    /// A losing move waiting for `y` to be played.
+    confirming: Option<usize>,
+    /// Whether finished games drain out of the board before a rematch.
+    drain: bool,
+    /// The earlier game being practised against after taking over from the
+    /// replay.
+    practice: Option<Game>,
+    /// The last game falling out of the board; nothing can be played until it
+    /// is gone.
+    draining: Option<animation::Drain>,
+    /// The connection to a networked opponent, who plays `remote`.
+    net: Option<net::Connection>,
+    remote: Option<Turn>,
+    /// The chat message being typed, while in chat mode.
+    chat: Option<String>,
+    chat_log: Vec<(Turn, String)>,
+    /// Network games given up with `x`, kept apart from finished games.
+    aborted_games: u32,
+    /// Pasted moves still to play, with their 1-based place in the paste.
+    pasted: VecDeque<(usize, usize)>,
+    /// Frame at which the next pasted move may be played.
+    next_paste: u64,
+    /// Whether the text description of the board is shown.
+    describing: bool,
+    /// Whether the player panels are shown, or only a line about the turn.
+    panels: bool,
+    /// When the replay last advanced by itself, while auto-playing.
+    autoplay: Option<Instant>,
+    /// Index into [`REPLAY_DELAYS_MS`] of the auto-play delay.
+    replay_delay: usize,
+    /// Whether the game is drawn compactly below the prompt.
+    inline: bool,
+    /// Whether the drops per lane over the match are charted.
+    histogram: bool,
+    /// Whether the moves played so far are listed next to the board.
+    move_list: bool,
+    /// Moves of the list scrolled past at its bottom, 0 following the latest.
+    move_list_scroll: usize,
+    /// Drops per lane in the earlier games of the match.
+    match_drops: Vec<usize>,
+    /// Results of the earlier games of the match.
+    score: score::Score,
+    /// How long to wait for input before [`App::idle`].
+    idle_timeout: Option<Duration>,
+    idle_quit: bool,
+    /// Whether the board title counts each player's winning drops.
+    threat_count: bool,
+    /// Whether the lanes that win right away for the player to move, and
+    /// those the opponent would win in, are outlined on the board.
+    threat_outlines: bool,
+    /// Whether every disc shows the number of its move.
+    move_numbers: bool,
+    /// Whether every field is labelled with its `row,column` index.
+    coordinates: bool,
+    /// Ticks since the start, advancing animations.
+    frame: u64,
+    falling: Option<animation::Fall>,
+    /// Whether anything shown changed since the last draw.
+    dirty: bool,
+    /// Draw times shown in the corner with `--debug`.
+    frame_times: Option<timing::FrameTimes>,
+}
+
+/// A background AI search for the current position.
+struct Thinking {
+    events: Receiver<ai::SearchEvent>,
+    depth: u32,
+}
+
+impl App {
+    fn new(config: Config, game: Game) -> App {
+        App {
+            screen: Screen::Menu {
+                selected: MENU_PLAY,
+            },
+            quit: false,
+            game,
+            input: String::new(),
+            theme: config.theme,
+            message: None,
+            ai: config.ai.then_some(config.human.other()),
+            human: config.human,
+            difficulty: config.difficulty,
+            ai_time: config.ai_time,
+            exhibition: false,
+            paused: false,
+            last_move: Instant::now(),
+            watch_delay: config.watch_delay,
+            started: None,
+            game_time: None,
+            clock: config.clock,
+            clocks: None,
+            thinking: None,
+            mirrored: false,
+            rotated: false,
+            analysis: false,
+            evaluation: None,
+            analysing: None,
+            hinting: None,
+            hint: None,
+            assist: config.assist,
+            bell: config.bell,
+            animation: config.animation,
+            trail: config.trail,
+            geometry: geometry::Geometry::new(config.disc_scale),
+            celebrate: config.celebrate,
+            fireworks: None,
+            drain: config.drain,
+            confirm_losing: config.confirm_losing,
+            confirming: None,
+            puzzle: None,
+            walkthrough: None,
+            undone: None,
+            draining: None,
+            practice: None,
+            marker: config.marker,
+            net: None,
+            remote: None,
+            chat: None,
+            chat_log: Vec::new(),
+            aborted_games: 0,
+            pasted: VecDeque::new(),
+            next_paste: 0,
+            describing: false,
+            panels: config.panels,
+            autoplay: None,
+            replay_delay: 3,
+            inline: config.inline,
+            histogram: false,
+            move_list: false,
+            move_list_scroll: 0,
+            match_drops: Vec::new(),
+            score: score::Score::new(config.best_of),
+            idle_timeout: config.idle_timeout,
+            idle_quit: config.idle_quit,
+            move_numbers: false,
+            threat_count: false,
+            threat_outlines: false,
+            coordinates: config.debug,
+            frame: 0,
+            falling: None,
+            dirty: true,
+            frame_times: config.debug.then(|| timing::FrameTimes::new(TICK_RATE)),
+        }
+    }
+
+    pub fn run(config: Config, start: Game) -> io::Result<()> {
+        let replay = match &config.replay {
+            Some(path) => {
+                let script = match fs::read_to_string(path) {
+                    Ok(script) => script,
+                    Err(err) => {
+                        eprintln!("error: cannot read {}: {err}", path.display());
+                        std::process::exit(headless::EXIT_UNREADABLE);
+                    }
+                };
+                match headless::run(&script, start.clone()) {
+                    Ok(game) => Some(game),
+                    Err(err) => {
+                        eprintln!("error: {err}");
+                        std::process::exit(headless::EXIT_ILLEGAL_MOVE);
+                    }
+                }
+            }
+            None => None,
+        };
+        let puzzle = match &config.puzzle {
+            Some(path) => match fs::read_to_string(path)
+                .map_err(game::PersistenceError::from)
+                .and_then(|text| puzzle::Puzzle::parse(&text, config.rules))
+            {
+                Ok(puzzle) => Some(puzzle),
+                Err(err) => {
+                    eprintln!("error: {}: {err}", path.display());
+                    std::process::exit(headless::EXIT_UNREADABLE);
+                }
+            },
+            None => None,
+        };
+        let connection = match (config.host, &config.join) {
+            (Some(port), _) => match net::Connection::host(port) {
+                Ok(connection) => Some((connection, Turn::Blue)),
+                Err(err) => {
+                    eprintln!("error: cannot listen on port {port}: {err}");
+                    std::process::exit(headless::EXIT_UNAVAILABLE);
+                }
+            },
+            (None, Some(address)) => Some((net::Connection::join(address.clone()), Turn::Red)),
+            (None, None) => None,
+        };
+        let alternate_screen = config.alternate_screen;
+        // The board with its border, the status line and the input line.
+        let inline = config.inline.then(|| start.height() as u16 + 4);
+        let mut terminal = init_terminal(alternate_screen, inline)?;
+        let mut app = App::new(config, start);
+        if inline.is_some() {
+            app.screen = Screen::Playing;
+        }
+        app.announce_result();
+        if let Some((connection, remote)) = connection {
+            app.net = Some(connection);
+            app.remote = Some(remote);
+            app.screen = Screen::Playing;
+        }
+        if let Some(puzzle) = puzzle {
+            app.message = Some(format!(
+                "Puzzle: find the best move for {}",
+                puzzle.game.turn().name()
+            ));
+            app.game = puzzle.game;
+            app.puzzle = Some(puzzle.solutions);
+            app.screen = Screen::Playing;
+        }
+        if let Some(game) = replay {
+            app.game = game;
+            app.start_replay(0);
+        }
+        let mut last_tick = Instant::now();
+        let mut last_input = Instant::now();
+        // Time spent on anything but waiting for input since the last tick.
+        let mut busy = Duration::ZERO;
+        loop {
+            let pass = Instant::now();
+            if app.dirty {
+                let _ = terminal.draw(|frame| app.ui(frame));
+                if let Some(times) = &mut app.frame_times {
+                    times.drawn(pass, pass.elapsed());
+                }
+                app.dirty = false;
+            }
+            let timeout = TICK_RATE.saturating_sub(last_tick.elapsed());
+            let waiting = Instant::now();
+            let input = event::poll(timeout)?;
+            let waited = waiting.elapsed();
+            if input {
+                match event::read()? {
+                    Event::Key(key) => {
+                        app.handle_key(key.code);
+                        app.dirty = true;
+                        last_input = Instant::now();
+                    }
+                    Event::Paste(text) => {
+                        app.paste(&text);
+                        app.dirty = true;
+                        last_input = Instant::now();
+                    }
+                    Event::Resize(..) => app.dirty = true,
+                    _ => {}
+                }
+            }
+            if app
+                .idle_timeout
+                .is_some_and(|timeout| last_input.elapsed() >= timeout)
+            {
+                app.idle();
+                app.dirty = true;
+                last_input = Instant::now();
+            }
+            if app.quit {
+                break;
+            }
+
+            app.update_clocks();
+            if let Screen::Playing = app.screen {
+                app.update_ai();
+                app.update_analysis();
+                app.update_hint();
+            }
+            app.update_net();
+
+            if last_tick.elapsed() >= TICK_RATE {
+                last_tick = Instant::now();
+                app.tick();
+                busy += pass.elapsed().saturating_sub(waited);
+                if let Some(times) = &mut app.frame_times {
+                    times.ticked(busy);
+                }
+                busy = Duration::ZERO;
+            } else {
+                busy += pass.elapsed().saturating_sub(waited);
+            }
+        }
+        if !alternate_screen {
+            // Leave the last frame in the scrollback, with the board below it.
+            let area = terminal.get_frame().size();
+            terminal.set_cursor(0, area.bottom().saturating_sub(1))?;
+            terminal.show_cursor()?;
+        }
+        restore_terminal(alternate_screen)?;
+        if app.inline {
+            // The board stays drawn above the prompt.
+            print!("\r\n");
+        } else if !alternate_screen {
+            print!("\r\n{}", headless::report(&app.game));
+        }
+        Ok(())
+    }
+
+    fn handle_key(&mut self, code: KeyCode) {
+        self.fireworks = None;
+        // Any key but quitting skips the drain.
+        if self.draining.take().is_some() && code != KeyCode::Char('q') {
+            return;
+        }
+        if let (Screen::Playing, Some((line, step))) = (&self.screen, &mut self.walkthrough) {
+            let moves = line.history().len();
+            match code {
+                KeyCode::Char('q') => self.quit = true,
+                KeyCode::Left | KeyCode::Backspace => *step = step.saturating_sub(1),
+                KeyCode::Esc => *step = moves + 1,
+                _ => *step += 1,
+            }
+            if *step > moves {
+                self.walkthrough = None;
+                self.message = Some(format!(
+                    "Back to the puzzle - {} to move, find the best move",
+                    self.game.turn().name()
+                ));
+            } else {
+                self.message = Some(self.walkthrough_text());
+            }
+            return;
+        }
+        if let Some(lane) = self.confirming.take() {
+            self.message = None;
+            if code == KeyCode::Char('y') && self.play(lane).is_ok() {
+                self.input.clear();
+            }
+            return;
+        }
+        if let (Screen::Playing, Some(chat)) = (&self.screen, &mut self.chat) {
+            match code {
+                KeyCode::Esc => self.chat = None,
+                KeyCode::Enter => {
+                    if let (Some(connection), Some(remote)) = (&mut self.net, self.remote) {
+                        if !chat.is_empty() {
+                            connection.send_chat(chat);
+                            self.chat_log.push((remote.other(), chat.clone()));
+                        }
+                    }
+                    chat.clear();
+                }
+                KeyCode::Backspace => {
+                    chat.pop();
+                }
+                KeyCode::Char(c) if chat.chars().count() < CHAT_LIMIT => chat.push(c),
+                _ => {}
+            }
+            return;
+        }
+        match &mut self.screen {
+            Screen::Menu { selected } => match code {
+                KeyCode::Char('q') => self.quit = true,
+                KeyCode::Up => *selected = selected.saturating_sub(1),
+                KeyCode::Down => *selected = (*selected + 1).min(MENU_ITEMS.len() - 1),
+                KeyCode::Enter => {
+                    let item = *selected;
+                    self.choose(item);
+                }
+                KeyCode::Char(c) => {
+                    let item = c
+                        .to_digit(10)
+                        .and_then(|digit| (digit as usize).checked_sub(1));
+                    if let Some(item) = item.filter(|&item| item < MENU_ITEMS.len()) {
+                        self.choose(item);
+                    }
+                }
+                _ => {}
+            },
+            Screen::Playing => match code {
+                KeyCode::Char('q') => self.quit = true,
+                KeyCode::Char('e') => {
+                    self.message = Some(match self.export_frames(REPLAY_FILE) {
+                        Ok(()) => format!("Replay exported to {REPLAY_FILE}"),
+                        Err(err) => format!("Replay export failed: {err}"),
+                    });
+                }
+                // No lane has more digits than the lane count. Without
+                // gravity fields are picked with the arrow keys instead.
+                KeyCode::Char(c)
+                    if c.is_ascii_digit()
+                        && self.is_local_turn()
+                        && !self.game.rules().gravity.is_free()
+                        && MoveKind::split(&self.input).1.len()
+                            < self.game.lanes().to_string().len() =>
+                {
+                    self.input.push(c);
+                }
+                // In Pop Out the typed column is popped instead.
+                KeyCode::Char('p') if self.is_local_turn() && self.game.rules().pop_out => {
+                    let kind = match MoveKind::split(&self.input).0 {
+                        MoveKind::Pop => MoveKind::Drop,
+                        _ => MoveKind::Pop,
+                    };
+                    self.select_kind(kind);
+                }
+                KeyCode::Char('b')
+                    if self.is_local_turn() && self.game.rules().power_pieces > 0 =>
+                {
+                    let kind = match MoveKind::split(&self.input).0 {
+                        MoveKind::Bomb => MoveKind::Anvil,
+                        MoveKind::Anvil => MoveKind::Drop,
+                        _ => MoveKind::Bomb,
+                    };
+                    self.select_kind(kind);
+                }
+                KeyCode::Up | KeyCode::Down | KeyCode::Left | KeyCode::Right
+                    if self.is_local_turn() && self.game.rules().gravity.is_free() =>
+                {
+                    self.move_cursor(code);
+                }
+                KeyCode::Backspace => {
+                    self.input.pop();
+                }
+                KeyCode::Home if self.is_local_turn() => {
+                    let lane = self.game.legal_lanes().next();
+                    self.select(lane);
+                }
+                KeyCode::End if self.is_local_turn() => {
+                    let lane = self.game.legal_lanes().last();
+                    self.select(lane);
+                }
+                KeyCode::Enter if self.is_local_turn() => {
+                    self.turn();
+                }
+                KeyCode::Char('r') if self.game.is_over() && self.net.is_none() => self.rematch(),
+                KeyCode::Char('r') => self.redo(),
+                KeyCode::Char('t') if self.net.is_some() => self.chat = Some(String::new()),
+                KeyCode::Char('x') if self.net.is_some() => self.abort_network_game(),
+                KeyCode::Char('g') => self.resign(),
+                KeyCode::Char(' ') if self.exhibition && !self.game.is_over() => {
+                    self.toggle_pause()
+                }
+                KeyCode::Esc if self.exhibition => self.stop_exhibition(),
+                KeyCode::Esc if self.net_status() == Some(net::Status::Disconnected) => {
+                    self.net = None;
+                    self.remote = None;
+                    self.message = None;
+                    self.screen = Screen::Menu {
+                        selected: MENU_PLAY,
+                    };
+                }
+                KeyCode::Char('c') => self.copy_board(),
+                KeyCode::Char('v') => self.start_replay(self.game.history().len()),
+                KeyCode::Char('u') => self.undo(),
+                KeyCode::Char('a') => self.analysis = !self.analysis,
+                KeyCode::Char('d') => self.describing = !self.describing,
+                KeyCode::Char('h') => self.request_hint(),
+                KeyCode::Char('H') => self.histogram = !self.histogram,
+                KeyCode::Char('l') => self.move_list = !self.move_list,
+                KeyCode::PageUp if self.move_list => {
+                    self.move_list_scroll = (self.move_list_scroll + MOVE_LIST_PAGE)
+                        .min(self.game.history().len().saturating_sub(1));
+                }
+                KeyCode::PageDown if self.move_list => {
+                    self.move_list_scroll = self.move_list_scroll.saturating_sub(MOVE_LIST_PAGE);
+                }
+                KeyCode::F(1) => self.coordinates = !self.coordinates,
+                KeyCode::Char('n') => self.move_numbers = !self.move_numbers,
+                KeyCode::Char('i') => self.threat_count = !self.threat_count,
+                KeyCode::Char('!') => {
+                    self.threat_outlines = !self.threat_outlines;
+                    self.message = Some(if self.threat_outlines {
+                        "Threats outlined - wins in your color, blocks in the warning color"
+                            .to_string()
+                    } else {
+                        "Threats no longer outlined".to_string()
+                    });
+                }
+                KeyCode::Char('T') => self.next_theme(),
+                KeyCode::Char('f') => self.toggle_panels(),
+                KeyCode::Char('s') => self.reveal_solution(),
+                KeyCode::Char('w') => {
+                    let (assist, message) = match self.assist {
+                        Assist::Off => (Assist::Hint, "Assist: tell when you can win"),
+                        Assist::Hint => (Assist::Reveal, "Assist: show the winning column"),
+                        Assist::Reveal => (Assist::Off, "Assist off"),
+                    };
+                    self.assist = assist;
+                    self.message = Some(message.to_string());
+                }
+                KeyCode::Char('m') => {
+                    self.mirrored = !self.mirrored;
+                    self.message = Some(if self.mirrored {
+                        "Board mirrored - column 1 is drawn on the right".to_string()
+                    } else {
+                        "Board no longer mirrored".to_string()
+                    });
+                }
+                KeyCode::Char('o') => {
+                    self.rotated = !self.rotated;
+                    self.message = Some(if self.rotated {
+                        "Board rotated - columns and rows keep their numbers".to_string()
+                    } else {
+                        "Board no longer rotated".to_string()
+                    });
+                }
+                _ => {}
+            },
+            Screen::GameOver { selected } => match code {
+                KeyCode::Char('q') => self.quit = true,
+                // A rematch is not offered over the network.
+                KeyCode::Up => {
+                    *selected = selected
+                        .saturating_sub(1)
+                        .max(usize::from(self.net.is_some()))
+                }
+                KeyCode::Down => *selected = (*selected + 1).min(GAME_OVER_ITEMS.len() - 1),
+                KeyCode::Char('r') if self.net.is_none() => self.rematch(),
+                KeyCode::Enter if *selected == 0 && self.net.is_none() => self.rematch(),
+                KeyCode::Enter if *selected == 1 => self.back_to_menu(),
+                // Any other key leaves the summary to look at the board.
+                _ => self.screen = Screen::Playing,
+            },
+            Screen::Replay { position } => {
+                let moves = self.game.history().len();
+                match code {
+                    KeyCode::Char('q') => self.quit = true,
+                    KeyCode::Esc => {
+                        self.input.clear();
+                        self.screen = Screen::Playing;
+                    }
+                    KeyCode::Left => *position = position.saturating_sub(1),
+                    KeyCode::Right => *position = (*position + 1).min(moves),
+                    KeyCode::Home => *position = 0,
+                    KeyCode::End => *position = moves,
+                    KeyCode::Char(' ') if self.autoplay.is_some() => self.autoplay = None,
+                    KeyCode::Char(' ') => {
+                        // Playing from the end starts over.
+                        if *position == moves {
+                            *position = 0;
+                        }
+                        self.autoplay = Some(Instant::now());
+                    }
+                    KeyCode::Char('+' | '=') => {
+                        self.replay_delay = self.replay_delay.saturating_sub(1)
+                    }
+                    KeyCode::Char('-') => {
+                        self.replay_delay = (self.replay_delay + 1).min(REPLAY_DELAYS_MS.len() - 1)
+                    }
+                    KeyCode::Char('p') => {
+                        let position = *position;
+                        self.take_over(position);
+                    }
+                    KeyCode::Char(c) if c.is_ascii_digit() => self.input.push(c),
+                    KeyCode::Backspace => {
+                        self.input.pop();
+                    }
+                    KeyCode::Enter => match self.input.parse::<usize>() {
+                        Ok(target) if target <= moves => {
+                            *position = target;
+                            self.input.clear();
+                            self.message = None;
+                        }
+                        _ => {
+                            self.message = Some(format!(
+                                "No move {} - the game has {moves} moves",
+                                self.input
+                            ));
+                            self.input.clear();
+                        }
+                    },
+                    _ => {}
+                }
+            }
+            Screen::Settings { selected, settings } => match code {
+                KeyCode::Up => *selected = selected.saturating_sub(1),
+                KeyCode::Down => *selected = (*selected + 1).min(settings::ITEMS.len() - 1),
+                KeyCode::Left => settings.change(*selected, false),
+                KeyCode::Right | KeyCode::Enter => settings.change(*selected, true),
+                KeyCode::Esc | KeyCode::Char('q') => {
+                    let settings = *settings;
+                    self.apply_settings(settings);
+                    self.screen = Screen::Menu {
+                        selected: MENU_SETTINGS,
+                    };
+                }
+                _ => {}
+            },
+            Screen::Openings { selected } => match code {
+                KeyCode::Char('q') => self.quit = true,
+                KeyCode::Esc => {
+                    self.screen = Screen::Menu {
+                        selected: MENU_OPENINGS,
+                    }
+                }
+                KeyCode::Up => {
+                    *selected = selected.saturating_sub(1);
+                    self.message = None;
+                }
+                KeyCode::Down => {
+                    *selected = (*selected + 1).min(openings::OPENINGS.len() - 1);
+                    self.message = None;
+                }
+                KeyCode::Enter => {
+                    let opening = &openings::OPENINGS[*selected];
+                    self.load_opening(opening);
+                }
+                _ => {}
+            },
+            Screen::Tutorial { step, .. } => match code {
+                KeyCode::Char('q') => self.quit = true,
+                KeyCode::Esc => {
+                    self.screen = Screen::Menu {
+                        selected: MENU_TUTORIAL,
+                    }
+                }
+                KeyCode::Enter | KeyCode::Right | KeyCode::Char(' ') => {
+                    self.screen = if *step + 1 < tutorial::STEPS.len() {
+                        App::tutorial_step(*step + 1)
+                    } else {
+                        Screen::Menu {
+                            selected: MENU_PLAY,
+                        }
+                    };
+                }
+                KeyCode::Left => self.screen = App::tutorial_step(step.saturating_sub(1)),
+                _ => {}
+            },
+        }
+    }
+
+    fn start_replay(&mut self, position: usize) {
+        self.autoplay = None;
+        self.input.clear();
+        self.message = None;
+        self.screen = Screen::Replay { position };
+    }
+
+    /// Leaves the replay to play on from the position after `position`
+    /// moves, with the replayed game kept to compare against.
+    fn take_over(&mut self, position: usize) {
+        if self.mode() == mode::Mode::Network {
+            self.message = Some("Cannot play on from a replay in a network game".to_string());
+            return;
+        }
+        let line = self.practice.take().unwrap_or_else(|| self.game.clone());
+        self.game = line.at_move(position);
+        self.undone = None;
+        self.practice = Some(line);
+        self.thinking = None;
+        self.falling = None;
+        self.fireworks = None;
+        self.pasted.clear();
+        self.input.clear();
+        self.message = Some(format!("Playing on from move {position}"));
+        self.screen = Screen::Playing;
+    }
+
+    /// Whether the game still follows the one being practised.
+    fn practice_status(&self) -> String {
+        let Some(line) = &self.practice else {
+            return String::new();
+        };
+        match self.game.deviation(line) {
+            None => "On the earlier game's line  ".to_string(),
+            Some(index) => format!("Left the earlier game at move {}  ", index + 1),
+        }
+    }
+
+    fn tutorial_step(step: usize) -> Screen {
+        let mut game = Game::new();
+        for &column in tutorial::STEPS[step].moves {
+            let _ = game.drop(column);
+        }
+        Screen::Tutorial { step, game }
+    }
+
+    /// Opens the menu item with index `item`.
+    fn choose(&mut self, item: usize) {
+        match item {
+            MENU_PLAY | MENU_PLAY_COMPUTER => {
+                let computer = item == MENU_PLAY_COMPUTER;
+                // The network decides who the opponent is.
+                if self.net.is_none() && self.ai.is_some() != computer {
+                    self.set_computer_opponent(computer);
+                }
+                if self.game.ending() == Some(Ending::Aborted) {
+                    self.game = Game::with_rules(self.game.rules());
+                    self.undone = None;
+                    self.match_drops.clear();
+                    self.score.reset();
+                }
+                self.screen = Screen::Playing;
+            }
+            MENU_EXHIBITION => self.start_exhibition(),
+            MENU_TUTORIAL => self.screen = App::tutorial_step(0),
+            MENU_SETTINGS => {
+                self.screen = Screen::Settings {
+                    selected: 0,
+                    settings: self.settings(),
+                }
+            }
+            MENU_OPENINGS => {
+                self.message = None;
+                self.screen = Screen::Openings { selected: 0 };
+            }
+            MENU_QUIT => self.quit = true,
+            _ => {}
+        }
+    }
+
+    /// Whether the player to move sits at this keyboard. Only then may moves
+    /// be typed; otherwise the AI is playing.
+    fn is_local_turn(&self) -> bool {
+        let connected = matches!(self.net_status(), None | Some(net::Status::Connected(_)));
+        connected && !self.is_computer_turn() && self.remote != Some(self.game.turn())
+    }
+
+    fn is_computer_turn(&self) -> bool {
+        self.exhibition || self.ai == Some(self.game.turn())
+    }
+
+    fn net_status(&self) -> Option<net::Status> {
+        self.net.as_ref().map(net::Connection::status)
+    }
+
+    pub fn turn(&mut self) {
+        let (kind, column) = MoveKind::split(&self.input);
+        if kind != MoveKind::Drop {
+            let column = column.to_string();
+            self.play_special(kind, &column);
+            return;
+        }
+        let lane = match self.game.parse_lane(&self.input) {
+            Ok(lane) => lane,
+            Err(_) if self.input.is_empty() => return,
+            Err(err) => {
+                self.message = Some(format!("Cannot play {}: {err}", self.input));
+                return;
+            }
+        };
+        let solved = match &self.puzzle {
+            Some(solutions) if !solutions.contains(&lane) => {
+                self.message = Some("Try again - press s to see the solution".to_string());
+                self.input.clear();
+                return;
+            }
+            Some(_) => true,
+            None => false,
+        };
+        if self.needs_confirming(lane) {
+            self.confirming = Some(lane);
+            self.message = Some("This move loses - play anyway? (y/n)".to_string());
+            return;
+        }
+
+        self.message = None;
+        if let Err(err) = self.play(lane) {
+            self.message = Some(format!("Cannot play {}: {err}", self.game.lane_label(lane)));
+            return;
+        }
+        self.input = String::new();
+        if solved {
+            self.puzzle = None;
+            self.walkthrough = None;
+            self.message = Some(match self.message.take() {
+                Some(result) => format!("Correct! {result}"),
+                None => "Correct!".to_string(),
+            });
+        }
+    }
+
+    /// Makes the typed move a pop, bomb or anvil, or a plain drop again,
+    /// keeping the column.
+    fn select_kind(&mut self, kind: MoveKind) {
+        let column = MoveKind::split(&self.input).1;
+        self.input = kind.prefix().into_iter().chain(column.chars()).collect();
+    }
+
+    /// Pops the typed `column` out in Pop Out, or drops a power piece into
+    /// it. A puzzle is solved by a plain drop.
+    fn play_special(&mut self, kind: MoveKind, column: &str) {
+        if self.puzzle.is_some() {
+            self.message = Some("Try again - press s to see the solution".to_string());
+            self.input.clear();
+            return;
+        }
+        let played = self
+            .game
+            .parse_lane(column)
+            .and_then(|column| self.game.play(kind, column));
+        if let Err(err) = played {
+            self.message = Some(match kind {
+                MoveKind::Pop => format!("Cannot pop {column}: {err}"),
+                _ => format!("Cannot drop the {} into {column}: {err}", kind.name()),
+            });
+            return;
+        }
+        self.message = None;
+        self.input.clear();
+        self.after_move();
+    }
+
+    /// Gives up on the puzzle: plays its first solution and the engine's
+    /// best play after it to the end, to be stepped through from the puzzle
+    /// position. Afterwards the puzzle can be tried again.
+    fn reveal_solution(&mut self) {
+        let Some(&first) = self.puzzle.as_ref().and_then(|solutions| solutions.first()) else {
+            return;
+        };
+        let mut line = self.game.clone();
+        if line.drop(first).is_err() {
+            return;
+        }
+        for lane in ai::best_line(&line, ai::ANALYSIS_DEPTH) {
+            let _ = line.drop(lane);
+        }
+        self.walkthrough = Some((line, 1));
+        self.input.clear();
+        self.message = Some(self.walkthrough_text());
+    }
+
+    /// Tells which move of the solution walkthrough is shown and how to go on.
+    fn walkthrough_text(&self) -> String {
+        let Some((line, step)) = &self.walkthrough else {
+            return String::new();
+        };
+        let moves = line.history().len();
+        let shown = match line.history()[..*step].last() {
+            None => "the puzzle position".to_string(),
+            Some(m) => {
+                let lane = line.rules().lane(m.row, m.column);
+                // The player to move at the start is the one solving it.
+                let verb = if m.player == self.game.turn() {
+                    "plays"
+                } else {
+                    "defends with"
+                };
+                format!("{} {verb} {}", m.player.name(), line.lane_label(lane))
+            }
+        };
+        let result = if *step == moves {
+            format!(" - {}", line.result_text())
+        } else {
+            String::new()
+        };
+        format!("Solution walkthrough {step}/{moves}: {shown}{result} (Enter/←/Esc)")
+    }
+
+    /// Whether dropping into `lane` should be confirmed first: it lets the
+    /// opponent win at once while another move would not.
+    fn needs_confirming(&self, lane: usize) -> bool {
+        self.confirm_losing
+            && self.net.is_none()
+            && self.game.hands_over_win(lane)
+            && self
+                .game
+                .legal_lanes()
+                .any(|other| !self.game.hands_over_win(other))
+    }
+
+    /// Drops a local player's disc into `lane` and tells the opponent.
+    fn play(&mut self, lane: usize) -> Result<(), MoveError> {
+        self.game.drop(lane)?;
+        if let Some(connection) = &mut self.net {
+            connection.send_move(lane);
+        }
+        self.after_move();
+        Ok(())
+    }
+
+    /// Handles pasted text: appended to a chat message being typed, or
+    /// otherwise read as moves with [`headless::parse_moves`] and played
+    /// one by one.
+    fn paste(&mut self, text: &str) {
+        if !matches!(self.screen, Screen::Playing) {
+            return;
+        }
+        if let Some(chat) = &mut self.chat {
+            let room = CHAT_LIMIT.saturating_sub(chat.chars().count());
+            chat.extend(text.chars().filter(|c| !c.is_control()).take(room));
+            return;
+        }
+        match headless::parse_moves(text, &self.game) {
+            Ok(lanes) => {
+                self.message = Some(format!("Playing {} pasted moves", lanes.len()));
+                self.pasted = lanes
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, lane)| (index + 1, lane))
+                    .collect();
+                self.next_paste = self.frame;
+            }
+            Err(err) => self.message = Some(format!("Cannot paste moves: {err}")),
+        }
+    }
+
+    /// Plays the next pasted move once the previous one had time to show,
+    /// waiting while the opponent is to move. Stops at the first move that
+    /// cannot be played.
+    fn play_pasted(&mut self) {
+        if self.frame < self.next_paste || !self.is_local_turn() || self.draining.is_some() {
+            return;
+        }
+        let Some((position, lane)) = self.pasted.pop_front() else {
+            return;
+        };
+        self.next_paste = self.frame + PASTE_DELAY;
+        self.dirty = true;
+        if let Err(error) = self.play(lane) {
+            let skipped = self.pasted.len() + 1;
+            let err = headless::ScriptError {
+                position,
+                token: (lane + 1).to_string(),
+                error,
+            };
+            self.message = Some(match error {
+                MoveError::GameOver => format!(
+                    "{}! Skipped {skipped} pasted moves after the end",
+                    self.game.result_text()
+                ),
+                _ => format!("Stopped pasting at {err}"),
+            });
+            self.pasted.clear();
+        }
+    }
+
+    /// Runs the clock of the player to move while a game with clocks is
+    /// played, and ends it when their time is up. The clocks are full until
+    /// the first move and stand outside the game screen. Network games and
+    /// puzzles have no clocks.
+    fn update_clocks(&mut self) {
+        let Some(limit) = self
+            .clock
+            .filter(|_| self.net.is_none() && self.puzzle.is_none())
+        else {
+            self.clocks = None;
+            return;
+        };
+        let clocks = self.clocks.get_or_insert_with(|| clock::Clocks::new(limit));
+        if self.game.history().is_empty() {
+            *clocks = clock::Clocks::new(limit);
+            return;
+        }
+        let running = matches!(self.screen, Screen::Playing)
+            && !self.game.is_over()
+            && !(self.exhibition && self.paused)
+            && self.walkthrough.is_none()
+            && self.draining.is_none();
+        if !running {
+            clocks.stop();
+            return;
+        }
+        let player = self.game.turn();
+        let shown = clocks.describe(player);
+        clocks.run(player, Instant::now());
+        if clocks.describe(player) != shown {
+            self.dirty = true;
+        }
+        if clocks.flagged() == Some(player) {
+            self.thinking = None;
+            self.game.end(Ending::OutOfTime(player));
+            self.announce_result();
+        }
+    }
+
+    /// Starts a search when it is the AI's turn and plays its move once the
+    /// search finishes.
+    fn update_ai(&mut self) {
+        if self.thinking.is_none() {
+            // An exhibition waits after every move so it can be followed.
+            let waiting =
+                self.exhibition && (self.paused || self.last_move.elapsed() < self.watch_delay);
+            if self.is_computer_turn()
+                && !self.game.is_over()
+                && self.draining.is_none()
+                && !waiting
+            {
+                self.thinking = Some(Thinking {
+                    events: ai::spawn_search(self.game.clone(), self.difficulty, self.ai_time),
+                    depth: 0,
+                });
+                self.dirty = true;
+            }
+            return;
+        }
+        let Some(thinking) = &mut self.thinking else {
+            return;
+        };
+        let mut chosen = None;
+        while let Ok(event) = thinking.events.try_recv() {
+            self.dirty = true;
+            match event {
+                ai::SearchEvent::Completed { depth } => thinking.depth = depth,
+                ai::SearchEvent::Finished(choice) => chosen = Some(choice),
+            }
+        }
+        if let Some(choice) = chosen {
+            self.thinking = None;
+            let Some(choice) = choice else {
+                if let Some((kind, column)) = ai::fallback_move(&self.game) {
+                    if self.game.play(kind, column).is_ok() {
+                        self.after_move();
+                    }
+                }
+                return;
+            };
+            let mover = self.game.turn();
+            if self.game.drop(choice.lane).is_ok() {
+                if let Some(score) = choice.score {
+                    self.game.annotate_last(match mover {
+                        Turn::Red => score,
+                        Turn::Blue => -score,
+                    });
+                }
+                self.after_move();
+            }
+        }
+    }
+
+    /// Keeps the analysis readout up to date with the position, searching
+    /// only while analysis mode is on.
+    fn update_analysis(&mut self) {
+        if !self.analysis {
+            return;
+        }
+        let position = position_key(&self.game);
+        if let Some((for_position, events)) = &self.analysing {
+            if let Ok(analysis) = events.try_recv() {
+                self.evaluation = Some((*for_position, analysis));
+                self.analysing = None;
+                self.dirty = true;
+            }
+        }
+        let evaluated = matches!(&self.evaluation, Some((key, _)) if *key == position);
+        let searching = matches!(&self.analysing, Some((key, _)) if *key == position);
+        if !evaluated && !searching {
+            self.analysing = Some((position, ai::spawn_analysis(self.game.clone())));
+        }
+    }
+
+    /// Asks the engine which move it would play for the local player, at the
+    /// chosen difficulty and thinking time.
+    fn request_hint(&mut self) {
+        if self.puzzle.is_some() {
+            self.message = Some("No hints in a puzzle - press s to see the solution".to_string());
+            return;
+        }
+        if !self.is_local_turn() || self.game.is_over() || self.walkthrough.is_some() {
+            return;
+        }
+        self.hint = None;
+        self.hinting = Some((
+            position_key(&self.game),
+            ai::spawn_search(self.game.clone(), self.difficulty, self.ai_time),
+        ));
+        self.message = Some("Looking for a good move...".to_string());
+    }
+
+    /// Marks the suggested lane once the search for it is done, unless the
+    /// position changed in the meantime.
+    fn update_hint(&mut self) {
+        let Some((position, events)) = &self.hinting else {
+            return;
+        };
+        let mut chosen = None;
+        while let Ok(event) = events.try_recv() {
+            if let ai::SearchEvent::Finished(choice) = event {
+                chosen = Some(choice);
+            }
+        }
+        let Some(choice) = chosen else {
+            return;
+        };
+        let position = *position;
+        self.hinting = None;
+        self.dirty = true;
+        if position != position_key(&self.game) {
+            return;
+        }
+        self.message = Some(match choice {
+            Some(choice) => {
+                self.hint = Some((position, choice.lane, self.frame + HINT_FRAMES));
+                format!("Hint: {}", self.game.lane_label(choice.lane))
+            }
+            None => "No move to suggest".to_string(),
+        });
+    }
+
+    /// Plays the opponent's moves as they arrive over the network.
+    fn update_net(&mut self) {
+        let Some(connection) = &mut self.net else {
+            return;
+        };
+        for event in connection.poll() {
+            self.dirty = true;
+            match event {
+                net::NetEvent::Connected => {}
+                net::NetEvent::Move(lane) => {
+                    if self.remote == Some(self.game.turn()) && self.game.drop(lane).is_ok() {
+                        self.after_move();
+                    }
+                }
+                net::NetEvent::Chat(text) => {
+                    if let Some(remote) = self.remote {
+                        self.chat_log.push((remote, text));
+                    }
+                }
+                net::NetEvent::Resigned => {
+                    if let Some(remote) = self.remote {
+                        self.game.end(Ending::Resigned(remote));
+                        self.announce_result();
+                    }
+                }
+                net::NetEvent::Disconnected => {
+                    self.message = Some(
+                        "Opponent disconnected - press q to quit or Esc for the menu".to_string(),
+                    );
+                }
+            }
+        }
+    }
+
+    /// Advances animations by one frame, redrawing while they play.
+    fn tick(&mut self) {
+        self.frame += 1;
+        if self
+            .hint
+            .as_ref()
+            .is_some_and(|&(_, _, until)| self.frame >= until)
+        {
+            self.hint = None;
+            self.dirty = true;
+        }
+        if let Screen::Playing = self.screen {
+            self.play_pasted();
+        }
+        self.autoplay_replay();
+        if let Some(fall) = self.falling {
+            self.dirty = true;
+            if fall.is_done(self.frame) {
+                self.falling = None;
+            }
+        }
+        if let Some(fireworks) = self.fireworks {
+            self.dirty = true;
+            if fireworks.is_done(self.frame) {
+                self.fireworks = None;
+            }
+        }
+        if let Some(drain) = &self.draining {
+            self.dirty = true;
+            if drain.is_done(self.frame) {
+                self.draining = None;
+            }
+        }
+        if self.celebrate.flashes()
+            && self.game.winner().is_some()
+            && self.frame.is_multiple_of(animation::FLASH_FRAMES)
+        {
+            self.dirty = true;
+        }
+    }
+
+    /// Steps an auto-playing replay once the delay has passed, stopping at
+    /// the end of the game.
+    fn autoplay_replay(&mut self) {
+        let delay = Duration::from_millis(REPLAY_DELAYS_MS[self.replay_delay]);
+        let (Screen::Replay { position }, Some(last)) = (&mut self.screen, self.autoplay) else {
+            self.autoplay = None;
+            return;
+        };
+        if last.elapsed() < delay {
+            return;
+        }
+        let moves = self.game.history().len();
+        *position = (*position + 1).min(moves);
+        self.autoplay = (*position < moves).then(Instant::now);
+        self.dirty = true;
+    }
+
+    /// Whether the engine evaluates the moves it plays, as against the
+    /// computer.
+    fn evaluates_moves(&self) -> bool {
+        matches!(self.mode(), mode::Mode::Computer | mode::Mode::Exhibition)
+    }
+
+    fn mode(&self) -> mode::Mode {
+        if self.exhibition {
+            return mode::Mode::Exhibition;
+        }
+        mode::Mode::new(self.net.is_some(), self.ai.is_some())
+    }
+
+    /// Lets the computer play both sides of a new game.
+    fn start_exhibition(&mut self) {
+        self.game = Game::with_rules(self.game.rules());
+        self.undone = None;
+        self.match_drops.clear();
+        self.score.reset();
+        self.practice = None;
+        self.puzzle = None;
+        self.walkthrough = None;
+        self.thinking = None;
+        self.input.clear();
+        self.exhibition = true;
+        self.paused = false;
+        self.last_move = Instant::now();
+        self.message = Some("AI vs AI - Space: pause, Esc: stop".to_string());
+        self.screen = Screen::Playing;
+    }
+
+    /// Starts a game from `opening`, played on the current rules, or tells
+    /// why it does not fit them.
+    fn load_opening(&mut self, opening: &openings::Opening) {
+        let game = match opening.play(Game::with_rules(self.game.rules())) {
+            Ok(game) => game,
+            Err(err) => {
+                self.message = Some(format!("{} does not fit this board: {err}", opening.name));
+                return;
+            }
+        };
+        self.game = game;
+        self.undone = None;
+        self.match_drops.clear();
+        self.score.reset();
+        self.practice = None;
+        self.puzzle = None;
+        self.walkthrough = None;
+        self.thinking = None;
+        self.falling = None;
+        self.fireworks = None;
+        self.draining = None;
+        self.input.clear();
+        self.clocks = None;
+        self.started = Some(Instant::now());
+        self.message = Some(format!(
+            "{} - {} to move",
+            opening.name,
+            self.game.turn().name()
+        ));
+        self.screen = Screen::Playing;
+    }
+
+    /// Ends the exhibition and leaves its board for a game of one's own.
+    fn stop_exhibition(&mut self) {
+        self.exhibition = false;
+        self.thinking = None;
+        self.falling = None;
+        self.fireworks = None;
+        self.game = Game::with_rules(self.game.rules());
+        self.undone = None;
+        self.match_drops.clear();
+        self.score.reset();
+        self.message = None;
+        self.screen = Screen::Menu {
+            selected: MENU_PLAY,
+        };
+    }
+
+    fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+        // A search running now would play after the pause.
+        self.thinking = None;
+        self.message = Some(if self.paused {
+            "Paused - Space: resume, Esc: stop".to_string()
+        } else {
+            "AI vs AI - Space: pause, Esc: stop".to_string()
+        });
+    }
+
+    /// Takes back the last move, and against the computer also its reply,
+    /// so it is the player's turn again.
+    fn undo(&mut self) {
+        if !self.mode().allows_undo() {
+            self.message = Some("Undo not allowed here".to_string());
+            return;
+        }
+        // Undoing further along the line keeps the moves after it.
+        let on_line = self
+            .undone
+            .as_ref()
+            .is_some_and(|undone| self.redo_move(undone).is_some());
+        if !on_line {
+            self.undone = Some(self.game.clone());
+        }
+        if self.game.undo().is_none() {
+            self.message = Some("Nothing to undo".to_string());
+            return;
+        }
+        while self.is_computer_turn() && self.game.undo().is_some() {}
+        self.thinking = None;
+        self.falling = None;
+        self.fireworks = None;
+        self.pasted.clear();
+        self.input.clear();
+        self.message = None;
+    }
+
+    /// Plays the next undone move again, and the computer's replies after
+    /// it, as they were played.
+    fn redo(&mut self) {
+        if !self.mode().allows_undo() {
+            self.message = Some("Redo not allowed here".to_string());
+            return;
+        }
+        let Some(undone) = self.undone.take() else {
+            self.message = Some("Nothing to redo".to_string());
+            return;
+        };
+        let mut redone = false;
+        while let Some(m) = self.redo_move(&undone) {
+            let lane = self.game.rules().lane(m.row, m.column);
+            if self.game.play(m.kind, lane).is_err() {
+                break;
+            }
+            if let Some(eval) = m.eval {
+                self.game.annotate_last(eval);
+            }
+            redone = true;
+            if !self.is_computer_turn() {
+                break;
+            }
+        }
+        self.undone = Some(undone);
+        if !redone {
+            self.message = Some("Nothing to redo".to_string());
+            return;
+        }
+        self.thinking = None;
+        self.pasted.clear();
+        self.input.clear();
+        self.message = None;
+        self.after_move();
+    }
+
+    /// The move of `undone` after the current position, if the game has not
+    /// left its line since.
+    fn redo_move(&self, undone: &Game) -> Option<Move> {
+        let played = self.game.history();
+        let &m = undone.history().get(played.len())?;
+        let same = |a: &Move, b: &Move| {
+            (a.player, a.row, a.column, a.kind) == (b.player, b.row, b.column, b.kind)
+        };
+        let on_line = undone.rules() == self.game.rules()
+            && undone.at_move(played.len()).hash() == self.game.hash()
+            && undone.history().iter().zip(played).all(|(a, b)| same(a, b));
+        on_line.then_some(m)
+    }
+
+    /// The current options, as the settings screen starts out.
+    fn settings(&self) -> Settings {
+        let rules = self.game.rules();
+        Settings {
+            rows: rules.height,
+            columns: rules.width,
+            connect: rules.connect,
+            ai: self.ai.is_some(),
+            human: self.human,
+            difficulty: self.difficulty,
+            ai_time: self.ai_time,
+            theme: self.theme,
+            marker: self.marker,
+            assist: self.assist,
+            bell: self.bell,
+            animation: self.animation,
+            panels: self.panels,
+            clock: self.clock,
+        }
+    }
+
+    /// Switches to the next theme and saves it for the next launch.
+    fn next_theme(&mut self) {
+        let mut settings = self.settings();
+        settings.change(settings::THEME, true);
+        self.theme = settings.theme;
+        self.message = Some(match settings.save() {
+            Ok(_) => format!("Theme: {}", self.theme.name),
+            Err(err) => format!("Theme: {} - not saved: {err}", self.theme.name),
+        });
+    }
+
+    /// Lets the computer play the side the human does not, or leaves both to
+    /// the keyboard, and saves the choice.
+    fn set_computer_opponent(&mut self, computer: bool) {
+        let mut settings = self.settings();
+        settings.ai = computer;
+        self.ai = computer.then_some(self.human.other());
+        self.thinking = None;
+        let opponent = if computer { "the computer" } else { "a human" };
+        self.message = Some(match settings.save() {
+            Ok(_) => format!("Playing against {opponent}"),
+            Err(err) => format!("Playing against {opponent} - not saved: {err}"),
+        });
+    }
+
+    /// Shows or hides the player panels and saves the choice.
+    fn toggle_panels(&mut self) {
+        let mut settings = self.settings();
+        settings.change(settings::PANELS, true);
+        self.panels = settings.panels;
+        let shown = if self.panels { "shown" } else { "hidden" };
+        self.message = Some(match settings.save() {
+            Ok(_) => format!("Player panels {shown}"),
+            Err(err) => format!("Player panels {shown} - not saved: {err}"),
+        });
+    }
+
+    /// Takes over the options from the settings screen and saves them. A new
+    /// board size or line length starts a new game, except over the network
+    /// where both sides have to agree on the board.
+    fn apply_settings(&mut self, settings: Settings) {
+        self.difficulty = settings.difficulty;
+        self.ai_time = settings.ai_time;
+        self.theme = settings.theme;
+        self.marker = settings.marker;
+        self.assist = settings.assist;
+        self.bell = settings.bell;
+        self.animation = settings.animation;
+        self.panels = settings.panels;
+        if settings.clock != self.clock {
+            self.clock = settings.clock;
+            self.clocks = None;
+        }
+        let mut notes = Vec::new();
+        if self.net.is_none() {
+            self.human = settings.human;
+            let ai = settings.ai.then_some(settings.human.other());
+            if ai != self.ai {
+                self.ai = ai;
+                self.thinking = None;
+            }
+            let rules = settings.rules(self.game.rules());
+            let old = self.game.rules();
+            if (rules.height, rules.width, rules.connect) != (old.height, old.width, old.connect) {
+                self.game = Game::with_rules(rules);
+                self.undone = None;
+                self.match_drops.clear();
+                self.score.reset();
+                self.practice = None;
+                self.puzzle = None;
+                self.walkthrough = None;
+                self.falling = None;
+                self.fireworks = None;
+                self.pasted.clear();
+                self.input.clear();
+                notes.push(format!(
+                    "New {}x{} board, {} in a row",
+                    rules.height, rules.width, rules.connect
+                ));
+            }
+        }
+        notes.push(match settings.save() {
+            Ok(path) => format!("Settings saved to {}", path.display()),
+            Err(err) => format!("Settings not saved: {err}"),
+        });
+        self.message = Some(notes.join(" - "));
+    }
+
+    /// Gives the game up for the local player: the player to move when both
+    /// play here.
+    fn resign(&mut self) {
+        if self.game.is_over() || self.exhibition {
+            return;
+        }
+        let player = match self.remote.or(self.ai) {
+            Some(opponent) => opponent.other(),
+            None => self.game.turn(),
+        };
+        self.game.end(Ending::Resigned(player));
+        if let Some(connection) = &mut self.net {
+            connection.send_resign();
+        }
+        self.announce_result();
+    }
 
-impl Into<Field> for Turn {
-    fn into(self) -> Field {
-        match self {
-            Turn::Red => Field::Red,
-            Turn::Blue => Field::Blue,
+    /// Nobody has touched the keyboard for the idle timeout: quits, or
+    /// leaves a fresh board on the menu for whoever comes next.
+    fn idle(&mut self) {
+        if self.idle_quit {
+            self.quit = true;
+            return;
+        }
+        if self.net.is_some() {
+            self.abort_network_game();
+            return;
+        }
+        if !self.game.history().is_empty() || self.game.is_over() {
+            self.game = Game::with_rules(self.game.rules());
         }
+        self.undone = None;
+        self.match_drops.clear();
+        self.score.reset();
+        self.exhibition = false;
+        self.thinking = None;
+        self.practice = None;
+        self.puzzle = None;
+        self.walkthrough = None;
+        self.falling = None;
+        self.fireworks = None;
+        self.draining = None;
+        self.pasted.clear();
+        self.input.clear();
+        self.message = None;
+        self.screen = Screen::Menu {
+            selected: MENU_PLAY,
+        };
     }
-}
 
-struct App {
-    board: [[Field; 7]; 6],
-    turn: Turn,
-    input: String,
-}
+    /// Gives up on a network game, e.g. when the opponent stopped
+    /// responding, and returns to the menu. Playing again starts a fresh
+    /// board.
+    fn abort_network_game(&mut self) {
+        self.net = None;
+        self.remote = None;
+        self.chat = None;
+        self.falling = None;
+        self.fireworks = None;
+        self.aborted_games += 1;
+        self.game.end(Ending::Aborted);
+        self.message = Some(format!(
+            "Network game aborted ({} this session)",
+            self.aborted_games
+        ));
+        self.screen = Screen::Menu {
+            selected: MENU_PLAY,
+        };
+    }
 
-impl App {
-    fn new() -> App {
-        App {
-            board: [[Field::Empty; 7]; 6],
-            turn: Turn::Red,
-            input: String::new(),
+    fn after_move(&mut self) {
+        self.last_move = Instant::now();
+        // A different move than the undone one leaves nothing to redo.
+        if self
+            .undone
+            .as_ref()
+            .is_some_and(|undone| self.redo_move(undone).is_none())
+        {
+            self.undone = None;
         }
+        if self.game.history().len() == 1 {
+            self.started = Some(self.last_move);
+        }
+        self.falling = animation::Fall::last_move(&self.game, self.frame, self.animation);
+        let opponent_moved = self
+            .game
+            .history()
+            .last()
+            .is_some_and(|m| Some(m.player) == self.ai || Some(m.player) == self.remote);
+        if self.bell && opponent_moved {
+            print!("\x07");
+            let _ = stdout().flush();
+        }
+        self.announce_result();
     }
 
-    pub fn run() -> io::Result<()> {
-        let mut terminal = init_terminal()?;
-        let mut app = App::new();
-        let mut last_tick = Instant::now();
-        let tick_rate = Duration::from_millis(16);
-        loop {
-            let _ = terminal.draw(|frame| app.ui(frame));
-            let timeout = tick_rate.saturating_sub(last_tick.elapsed());
-            if event::poll(timeout)? {
-                if let Event::Key(key) = event::read()? {
-                    match key.code {
-                        KeyCode::Char('q') => break,
-                        KeyCode::Char(c) => {
-                            if c.is_digit(10) {
-                                app.input.push(c);
-                            }
-                        }
-                        KeyCode::Backspace => {
-                            app.input.pop();
-                        }
-                        KeyCode::Enter => {
-                            app.turn();
-                        }
-                        _ => {}
-                    }
-                }
+    /// Shows the result once the game is decided.
+    fn announce_result(&mut self) {
+        if self.game.winning_line().is_some() && self.celebrate.fireworks() {
+            self.fireworks = Some(animation::Fireworks::new(self.frame));
+        }
+        if self.game.is_over() {
+            self.game_time = self.started.map(|started| started.elapsed());
+            if !self.inline && matches!(self.screen, Screen::Playing) {
+                // A rematch is not offered over the network.
+                let selected = usize::from(self.net.is_some());
+                self.screen = Screen::GameOver { selected };
             }
+            let score = self.score.with(&self.game);
+            self.message = Some(match score.series_winner() {
+                // A rematch is not offered over the network.
+                _ if self.net.is_some() && self.inline => format!("{}!", self.game.result_text()),
+                _ if self.net.is_some() => {
+                    format!("{}! Enter: back to menu", self.game.result_text())
+                }
+                Some(winner) => format!(
+                    "{}! {} wins the series, {} - press r for a new one",
+                    self.game.result_text(),
+                    winner.name(),
+                    score.describe_match()
+                ),
+                None => format!(
+                    "{}! Press r for a rematch - {} starts",
+                    self.game.result_text(),
+                    self.game.rematch().turn().name()
+                ),
+            });
+        }
+    }
 
-            if last_tick.elapsed() >= tick_rate {
-                last_tick = Instant::now();
-            }
+    /// Replaces a finished game with [`Game::rematch`].
+    fn rematch(&mut self) {
+        self.practice = None;
+        self.puzzle = None;
+        self.walkthrough = None;
+        if self.drain && !self.game.history().is_empty() {
+            self.draining = Some(animation::Drain::new(self.game.clone(), self.frame));
         }
-        restore_terminal()
+        self.match_drops = self.drops();
+        self.score.record(&self.game);
+        // A decided series starts over.
+        if self.score.series_winner().is_some() {
+            self.score.reset();
+        }
+        self.game = self.game.rematch();
+        self.undone = None;
+        self.falling = None;
+        self.fireworks = None;
+        self.input.clear();
+        self.message = None;
+        self.screen = Screen::Playing;
     }
 
-    pub fn turn(&mut self) {
-        let column: usize = match self.input.parse() {
-            Ok(c) => c,
-            Err(_) => {
-                return;
-            }
+    /// Leaves a finished game for the menu, with a fresh board for the next
+    /// game. A network game ends with its connection.
+    fn back_to_menu(&mut self) {
+        if self.net.take().is_some() {
+            self.remote = None;
+            self.game = Game::with_rules(self.game.rules());
+            self.undone = None;
+            self.falling = None;
+            self.fireworks = None;
+            self.input.clear();
+        } else {
+            self.rematch();
+            // The menu does not show the old game falling out.
+            self.draining = None;
+        }
+        self.exhibition = false;
+        self.message = None;
+        self.screen = Screen::Menu {
+            selected: MENU_PLAY,
         };
+    }
 
-        if column == 0 || column > self.board[0].len() {
-            return;
+    /// Drops per lane over the match so far, this game included.
+    fn drops(&self) -> Vec<usize> {
+        let mut drops = self.game.lane_drops();
+        for (total, earlier) in drops.iter_mut().zip(&self.match_drops) {
+            *total += earlier;
         }
+        drops
+    }
 
-        let column = column - 1;
+    /// A bar per lane with the discs dropped into it over the match.
+    fn drops_chart(&self) -> impl Widget + '_ {
+        let drops = self.drops();
+        let bars: Vec<Bar> = drops
+            .iter()
+            .enumerate()
+            .map(|(lane, &count)| {
+                Bar::default()
+                    .label((lane + 1).to_string().into())
+                    .value(count as u64)
+            })
+            .collect();
+        let lane_name = self.game.rules().gravity.lane_name();
+        BarChart::default()
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(format!("Drops per {lane_name} this match"))
+                    .border_style(Style::default().fg(self.theme.grid)),
+            )
+            .data(BarGroup::default().bars(&bars))
+            .bar_width(2)
+            .bar_gap(1)
+            .bar_style(Style::default().fg(self.theme.player1))
+            .value_style(
+                Style::default()
+                    .fg(self.theme.background)
+                    .bg(self.theme.player1),
+            )
+    }
 
-        let mut i: usize = 0;
+    /// Copies [`Game::to_ascii`] to the system clipboard.
+    fn copy_board(&mut self) {
+        #[cfg(feature = "clipboard")]
+        let result = clipboard::copy(&self.game.to_ascii());
+        #[cfg(not(feature = "clipboard"))]
+        let result: Result<(), String> = Err("built without the clipboard feature".to_string());
+        self.message = Some(match result {
+            Ok(()) => "Board copied to the clipboard".to_string(),
+            Err(err) => format!("Cannot copy the board: {err}"),
+        });
+    }
 
-        loop {
-            if i >= self.board.len() {
-                return;
-            }
-            match self.board[i][column] {
-                Field::Empty => {
-                    break;
+    /// Writes one ASCII frame per move, followed by the final position,
+    /// separated by blank lines. In AI games every move is annotated with the
+    /// engine evaluation, or marked as not evaluated for human moves.
+    pub fn export_frames(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let history = self.game.history();
+        let mut text = format!("Start\n{}", self.game.at_move(0).to_ascii());
+        let changes = ai::eval_changes(history);
+        for (index, m) in history.iter().enumerate() {
+            let annotation = match m.eval {
+                _ if !self.evaluates_moves() => String::new(),
+                None => " (not evaluated)".to_string(),
+                Some(eval) => {
+                    let blunder = changes[index]
+                        .is_some_and(|(previous, eval)| ai::is_blunder(m.player, previous, eval));
+                    format!(
+                        " (eval {}{})",
+                        ai::format_eval(eval),
+                        if blunder { ", blunder" } else { "" }
+                    )
                 }
-                _ => {
-                    i += 1;
+            };
+            let played = match m.kind {
+                MoveKind::Drop => format!("-> col {}, row {}", m.column + 1, m.row + 1),
+                MoveKind::Pop => format!("pops col {}", m.column + 1),
+                kind => format!("-> {} col {}, row {}", kind.name(), m.column + 1, m.row + 1),
+            };
+            text += &format!(
+                "\n{}. {} {played}{annotation}\n{}",
+                index + 1,
+                m.player.name(),
+                self.game.at_move(index + 1).to_ascii()
+            );
+        }
+        text += &format!(
+            "\n{} after {} moves\n{}",
+            self.game.result_text(),
+            history.len(),
+            self.game.to_ascii()
+        );
+        fs::write(path, text)
+    }
+
+    fn ui(&self, frame: &mut Frame) {
+        match &self.screen {
+            Screen::Menu { selected } => self.render_menu(frame, *selected),
+            Screen::Playing if self.inline => self.render_inline(frame),
+            Screen::Playing => self.render_game(frame),
+            Screen::GameOver { selected } => {
+                self.render_game(frame);
+                if self.draining.is_none() {
+                    self.render_summary(frame, *selected);
                 }
             }
+            Screen::Tutorial { step, game } => self.render_tutorial(frame, *step, game),
+            Screen::Replay { position } => self.render_replay(frame, *position),
+            Screen::Settings { selected, settings } => {
+                self.render_settings(frame, *selected, settings)
+            }
+            Screen::Openings { selected } => self.render_openings(frame, *selected),
+        }
+        if let Some(times) = &self.frame_times {
+            // The previous frame's numbers, in the top right corner.
+            let text = times.describe(Instant::now());
+            let area = frame.size();
+            let width = (text.chars().count() as u16).min(area.width);
+            let corner = Rect::new(area.right() - width, area.y, width, 1);
+            frame.render_widget(Clear, corner);
+            frame.render_widget(Paragraph::new(text).reversed(), corner);
+        }
+    }
+
+    /// The game in as few lines as possible: the board with one character
+    /// per field, the status line and the move being typed.
+    fn render_inline(&self, frame: &mut Frame) {
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Min(0),
+                Constraint::Length(1),
+                Constraint::Length(1),
+            ])
+            .split(frame.size());
+        frame.render_widget(self.mini_board(&self.game, layout[0]), layout[0]);
+        frame.render_widget(self.status_bar(layout[1].width), layout[1]);
+        frame.render_widget(Paragraph::new(self.prompt_line()), layout[2]);
+    }
+
+    /// What the player panels tell about the turn, in one line: the move
+    /// being typed with any win hint, or that the computer is thinking.
+    fn prompt_line(&self) -> text::Line<'_> {
+        let player = self.game.turn();
+        let style = Style::default().fg(self.theme.player(player));
+        let highlight = Style::default().fg(self.theme.highlight);
+        if self.game.is_over() {
+            return text::Line::default();
         }
+        if let Some(thinking) = &self.thinking {
+            return text::Line::styled(
+                format!("{} thinking... depth {}", player.name(), thinking.depth),
+                style,
+            );
+        }
+        if !self.is_local_turn() {
+            return text::Line::styled(format!("{} to move", player.name()), style);
+        }
+        let prompt = format!(
+            "{} {}: ",
+            player.name(),
+            self.game.rules().gravity.lane_name()
+        );
+        let mut line = self.input_line(highlight);
+        line.spans.insert(0, Span::styled(prompt, style));
+        if let Some(hint) = self.win_hint(player) {
+            line.spans
+                .push(Span::styled(format!("  {hint}"), highlight.italic()));
+        }
+        line
+    }
 
-        self.board[i][column] = self.turn.into();
+    fn render_settings(&self, frame: &mut Frame, selected: usize, settings: &Settings) {
+        let area = centered_rect(40, settings::ITEMS.len() as u16 + 2, frame.size());
+        let items = settings::ITEMS
+            .iter()
+            .enumerate()
+            .map(|(item, name)| format!("{name:<20} {}", settings.value(item)));
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title("Settings")
+                    .title(
+                        block::Title::from("←/→ change, Esc save")
+                            .position(block::Position::Bottom),
+                    )
+                    .border_style(Style::default().fg(self.theme.grid)),
+            )
+            .highlight_style(Style::default().fg(self.theme.player1).bold())
+            .highlight_symbol("> ");
+        frame.render_stateful_widget(
+            list,
+            area,
+            &mut ListState::default().with_selected(Some(selected)),
+        );
+    }
 
-        self.input = String::new();
-        self.turn = match self.turn {
-            Turn::Red => Turn::Blue,
-            Turn::Blue => Turn::Red,
+    fn render_replay(&self, frame: &mut Frame, position: usize) {
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Min(0),
+                Constraint::Length(1),
+                Constraint::Length(3),
+            ])
+            .split(frame.size());
+
+        let shown = self.game.at_move(position);
+        self.render_board(frame, &shown, None, None, (0.0, 0.0), layout[0]);
+        frame.render_widget(self.replay_progress(position), layout[1]);
+        let last = match position
+            .checked_sub(1)
+            .map(|index| self.game.history()[index])
+        {
+            Some(m) => format!(
+                " - {}. {} {} col {}",
+                position,
+                m.player.name(),
+                match m.kind {
+                    MoveKind::Drop => "->".to_string(),
+                    MoveKind::Pop => "pops".to_string(),
+                    kind => format!("-> {}", kind.name()),
+                },
+                m.column + 1
+            ),
+            None => " - start".to_string(),
         };
+        frame.render_widget(
+            Paragraph::new(format!(
+                "Move {position}/{}{last}  jump to: {}",
+                self.game.history().len(),
+                self.input
+            ))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title("Replay")
+                    .title(
+                        block::Title::from("Space: auto-play, +/-: speed")
+                            .alignment(Alignment::Right),
+                    )
+                    .title(
+                        block::Title::from(
+                            "←/→: step, Home/End: start/end, number + Enter: jump, p: play on, Esc: back",
+                        )
+                        .position(block::Position::Bottom),
+                    ),
+            ),
+            layout[2],
+        );
     }
 
-    fn ui(&self, frame: &mut Frame) {
+    /// How far into the game the replay is, marking the first and last
+    /// position.
+    fn replay_progress(&self, position: usize) -> impl Widget {
+        let moves = self.game.history().len();
+        let label = match position {
+            0 => format!("start - 0 / {moves} moves"),
+            _ if position == moves => format!("end - {moves} / {moves} moves"),
+            _ => format!("{position} / {moves} moves"),
+        };
+        let delay = REPLAY_DELAYS_MS[self.replay_delay] as f64 / 1000.0;
+        let state = if self.autoplay.is_some() {
+            "playing"
+        } else {
+            "paused"
+        };
+        let label = format!("{label} - {state}, {delay} s per move");
+        let color = if position == moves {
+            self.theme.highlight
+        } else {
+            self.theme.idle
+        };
+        Gauge::default()
+            .ratio(if moves == 0 {
+                0.0
+            } else {
+                position as f64 / moves as f64
+            })
+            .label(label)
+            .use_unicode(true)
+            .gauge_style(Style::default().fg(color))
+    }
+
+    fn render_menu(&self, frame: &mut Frame, selected: usize) {
+        let area = centered_rect(24, MENU_ITEMS.len() as u16 + 2, frame.size());
+        let items = MENU_ITEMS
+            .iter()
+            .enumerate()
+            .map(|(index, item)| format!("{}. {item}", index + 1));
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(format!("{} in a row", self.game.rules().connect))
+                    .border_style(Style::default().fg(self.theme.grid)),
+            )
+            .highlight_style(Style::default().fg(self.theme.player1).bold())
+            .highlight_symbol("> ");
+        frame.render_stateful_widget(
+            list,
+            area,
+            &mut ListState::default().with_selected(Some(selected)),
+        );
+        if let Some(message) = &self.message {
+            let below = Rect {
+                x: 0,
+                y: (area.y + area.height).min(frame.size().height.saturating_sub(1)),
+                width: frame.size().width,
+                height: 1,
+            };
+            frame.render_widget(
+                Paragraph::new(message.as_str()).alignment(Alignment::Center),
+                below,
+            );
+        }
+    }
+
+    fn render_tutorial(&self, frame: &mut Frame, step: usize, game: &Game) {
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(4)])
+            .split(frame.size());
+
+        self.render_board(
+            frame,
+            game,
+            tutorial::STEPS[step].column,
+            None,
+            (0.0, 0.0),
+            layout[0],
+        );
+        frame.render_widget(
+            Paragraph::new(tutorial::STEPS[step].text)
+                .wrap(Wrap { trim: true })
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .title(format!("Tutorial {}/{}", step + 1, tutorial::STEPS.len()))
+                        .title(
+                            block::Title::from("Enter: next, Left: back, Esc: menu")
+                                .position(block::Position::Bottom),
+                        ),
+                ),
+            layout[1],
+        );
+    }
+
+    /// The openings to pick from, with the selected one shown on the board.
+    fn render_openings(&self, frame: &mut Frame, selected: usize) {
+        let opening = &openings::OPENINGS[selected];
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Min(0),
+                Constraint::Length(openings::OPENINGS.len() as u16 + 2),
+                Constraint::Length(4),
+            ])
+            .split(frame.size());
+
+        let empty = Game::with_rules(self.game.rules());
+        let (game, text) = match opening.play(empty.clone()) {
+            Ok(game) => (game, opening.text.to_string()),
+            Err(err) => (empty, format!("Does not fit this board: {err}")),
+        };
+        self.render_board(frame, &game, None, None, (0.0, 0.0), layout[0]);
+        let list = List::new(openings::OPENINGS.iter().map(|opening| opening.name))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title("Openings")
+                    .border_style(Style::default().fg(self.theme.grid)),
+            )
+            .highlight_style(Style::default().fg(self.theme.player1).bold())
+            .highlight_symbol("> ");
+        frame.render_stateful_widget(
+            list,
+            layout[1],
+            &mut ListState::default().with_selected(Some(selected)),
+        );
+        let message = self.message.as_deref().unwrap_or(&text);
+        frame.render_widget(
+            Paragraph::new(message).wrap(Wrap { trim: true }).block(
+                Block::default().borders(Borders::ALL).title(
+                    block::Title::from("Enter: play, Esc: menu").position(block::Position::Bottom),
+                ),
+            ),
+            layout[2],
+        );
+    }
+
+    fn render_game(&self, frame: &mut Frame) {
+        let description = self.describing.then(|| self.game.describe());
+        let description_height = description.as_ref().map_or(0, |text| {
+            (text.chars().count() / frame.size().width.max(1) as usize) as u16 + 2
+        });
         let main_layout = Layout::default()
             .direction(Direction::Vertical)
-            .constraints([Constraint::Percentage(95), Constraint::Percentage(5)])
+            .constraints([
+                Constraint::Min(0),
+                Constraint::Length(1),
+                Constraint::Length(description_height),
+                Constraint::Length(if self.histogram { 8 } else { 0 }),
+                Constraint::Length(if self.net.is_some() {
+                    CHAT_LINES + 2
+                } else {
+                    0
+                }),
+                // The player panels, or a line telling about the turn.
+                Constraint::Length(if self.panels { 5 } else { 1 }),
+            ])
             .split(frame.size());
 
         let controls_layout = Layout::default()
             .direction(Direction::Horizontal)
             .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
-            .split(main_layout[1]);
+            .split(main_layout[5]);
+        let board_layout = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Min(0),
+                Constraint::Length(if self.move_list { MOVE_LIST_WIDTH } else { 0 }),
+            ])
+            .split(main_layout[0]);
+
+        let ghost = self.selected_lane().filter(|_| self.is_local_turn());
+        match (&self.draining, &self.walkthrough) {
+            (Some(drain), _) => {
+                let shift = drain.shift(self.frame);
+                self.render_board(frame, drain.game(), None, None, shift, board_layout[0]);
+            }
+            (None, Some((line, step))) => {
+                let shown = line.at_move(*step);
+                self.render_board(frame, &shown, None, None, (0.0, 0.0), board_layout[0]);
+            }
+            (None, None) => self.render_board(
+                frame,
+                &self.game,
+                ghost,
+                self.falling,
+                (0.0, 0.0),
+                board_layout[0],
+            ),
+        }
+        frame.render_widget(self.status_bar(main_layout[1].width), main_layout[1]);
+        if let Some(text) = description {
+            frame.render_widget(
+                Paragraph::new(text).wrap(Wrap { trim: true }),
+                main_layout[2],
+            );
+        }
+        if self.move_list {
+            frame.render_widget(
+                self.move_list_panel(board_layout[1].height),
+                board_layout[1],
+            );
+        }
+        if self.histogram {
+            frame.render_widget(self.drops_chart(), main_layout[3]);
+        }
+        if self.net.is_some() {
+            frame.render_widget(self.chat_panel(), main_layout[4]);
+        }
+        if self.panels {
+            frame.render_widget(self.red_player_canvas(), controls_layout[0]);
+            frame.render_widget(self.blue_player_canvas(), controls_layout[1]);
+        } else {
+            frame.render_widget(Paragraph::new(self.prompt_line()), main_layout[5]);
+        }
+
+        if self.game.history().is_empty()
+            && self.draining.is_none()
+            && self.walkthrough.is_none()
+            && self.is_local_turn()
+        {
+            self.render_start_hint(frame, board_layout[0]);
+        }
+    }
+
+    /// The result of the finished game with its length and, when the engine
+    /// evaluated the moves, the blunders and the biggest change of the
+    /// evaluation. Lines that do not fit are cut off.
+    fn render_summary(&self, frame: &mut Frame, selected: usize) {
+        let history = self.game.history();
+        let mut lines = vec![self.game.result_text(), format!("Moves: {}", history.len())];
+        if let Some(time) = self.game_time {
+            let seconds = time.as_secs();
+            lines.push(format!("Time: {}:{:02}", seconds / 60, seconds % 60));
+        }
+        let score = self.score.with(&self.game);
+        if score.games() > 1 || score.first_to().is_some() {
+            lines.push(format!("Match: {}", score.describe_match()));
+        }
+        match (score.series_winner(), score.first_to()) {
+            (Some(winner), _) => lines.push(format!("{} wins the series!", winner.name())),
+            (None, Some(wins)) => lines.push(format!("Series: first to {wins} wins")),
+            (None, None) => {}
+        }
+        if self.evaluates_moves() {
+            let changes = ai::eval_changes(history);
+            let blunders = [Turn::Red, Turn::Blue].map(|player| {
+                let moves = || {
+                    history
+                        .iter()
+                        .zip(&changes)
+                        .filter(|(m, _)| m.player == player)
+                };
+                if moves().all(|(m, _)| m.eval.is_none()) {
+                    return format!("{} not evaluated", player.name());
+                }
+                let count = moves()
+                    .filter(|(m, change)| {
+                        change.is_some_and(|(previous, eval)| {
+                            ai::is_blunder(m.player, previous, eval)
+                        })
+                    })
+                    .count();
+                format!("{} {count}", player.name())
+            });
+            lines.push(format!("Blunders: {}", blunders.join(", ")));
+            let biggest = changes
+                .iter()
+                .enumerate()
+                .filter_map(|(index, change)| change.map(|change| (index, change)))
+                .max_by_key(|(_, (previous, eval))| eval.abs_diff(*previous));
+            if let Some((index, (previous, eval))) = biggest {
+                lines.push(format!(
+                    "Biggest swing: move {} ({}), {} -> {}",
+                    index + 1,
+                    history[index].player.name(),
+                    ai::format_eval(previous),
+                    ai::format_eval(eval)
+                ));
+            }
+        }
+        let hint = "Enter: choose, Esc: board";
+        // Room for the choices and the hint in the border as well.
+        let width = lines
+            .iter()
+            .map(|line| line.chars().count())
+            .chain(GAME_OVER_ITEMS.iter().map(|item| item.len() + 2))
+            .chain([hint.len().saturating_sub(2)])
+            .max()
+            .unwrap_or(0);
+        let mut lines: Vec<text::Line> = lines.into_iter().map(text::Line::from).collect();
+        lines.push(text::Line::default());
+        for (index, item) in GAME_OVER_ITEMS.iter().enumerate() {
+            let offered = index != 0 || self.net.is_none();
+            lines.push(if index == selected {
+                text::Line::styled(format!("> {item}"), Style::default().bold())
+            } else if offered {
+                text::Line::raw(format!("  {item}"))
+            } else {
+                text::Line::styled(format!("  {item}"), Style::default().dim())
+            });
+        }
+        let area = centered_rect(width as u16 + 4, lines.len() as u16 + 2, frame.size());
+        frame.render_widget(Clear, area);
+        frame.render_widget(
+            Paragraph::new(lines).block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title("Game over")
+                    .title(block::Title::from(hint).position(block::Position::Bottom))
+                    .border_style(Style::default().fg(self.theme.highlight))
+                    .padding(block::Padding::horizontal(1)),
+            ),
+            area,
+        );
+    }
+
+    /// The moves played so far, one per line with the latest at the bottom,
+    /// scrolled up by [`App::move_list_scroll`] moves.
+    fn move_list_panel(&self, height: u16) -> impl Widget {
+        let history = self.game.history();
+        let shown = height.saturating_sub(2) as usize;
+        let end = history
+            .len()
+            .saturating_sub(self.move_list_scroll.min(history.len().saturating_sub(1)));
+        let start = end.saturating_sub(shown);
+        let lines: Vec<text::Line> = history[start..end]
+            .iter()
+            .enumerate()
+            .map(|(index, m)| {
+                let played = match m.kind {
+                    MoveKind::Drop => "→".to_string(),
+                    MoveKind::Pop => "pops".to_string(),
+                    kind => format!("→ {}", kind.name()),
+                };
+                text::Line::styled(
+                    format!(
+                        "{}. {} {played} col {}",
+                        start + index + 1,
+                        m.player.name(),
+                        m.column + 1
+                    ),
+                    Style::default().fg(self.theme.player(m.player)),
+                )
+            })
+            .collect();
+        let title = if history.is_empty() {
+            "Moves".to_string()
+        } else {
+            format!("Moves {}-{} of {}", start + 1, end, history.len())
+        };
+        Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(title)
+                .title(block::Title::from("PgUp/PgDn").position(block::Position::Bottom))
+                .border_style(Style::default().fg(self.theme.idle)),
+        )
+    }
+
+    /// The last chat messages in their sender's color, then the message
+    /// being typed.
+    fn chat_panel(&self) -> impl Widget + '_ {
+        let shown = match self.chat {
+            Some(_) => CHAT_LINES as usize - 1,
+            None => CHAT_LINES as usize,
+        };
+        let mut lines: Vec<text::Line> = self.chat_log[self.chat_log.len().saturating_sub(shown)..]
+            .iter()
+            .map(|(sender, message)| {
+                text::Line::from(vec![
+                    Span::styled(
+                        format!("{}: ", sender.name()),
+                        Style::default().fg(self.theme.player(*sender)),
+                    ),
+                    Span::raw(message.as_str()),
+                ])
+            })
+            .collect();
+        let hint = match &self.chat {
+            Some(chat) => {
+                lines.push(text::Line::styled(
+                    format!("> {chat}_"),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ));
+                "Enter: send, Esc: back to the game"
+            }
+            None => "t: chat",
+        };
+        Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Chat")
+                .title(block::Title::from(hint).position(block::Position::Bottom))
+                .border_style(Style::default().fg(self.theme.idle)),
+        )
+    }
+
+    fn render_start_hint(&self, frame: &mut Frame, area: Rect) {
+        let text = if self.game.rules().gravity.is_free() {
+            format!(
+                "{} to start - pick a field with the arrow keys and press Enter",
+                self.game.turn().name()
+            )
+        } else {
+            format!(
+                "{} to start - type a {} 1-{} and press Enter",
+                self.game.turn().name(),
+                self.game.rules().gravity.lane_name(),
+                self.game.lanes()
+            )
+        };
+        let area = centered_rect(text.len() as u16 + 4, 3, area);
+        frame.render_widget(Clear, area);
+        frame.render_widget(
+            Paragraph::new(text)
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .border_style(Style::default().fg(self.theme.player(self.game.turn()))),
+                )
+                .alignment(Alignment::Center),
+            area,
+        );
+    }
+
+    fn status_bar(&self, width: u16) -> impl Widget + '_ {
+        let connection = match self.net_status() {
+            None => String::new(),
+            Some(net::Status::Waiting) => "Waiting for opponent…  ".to_string(),
+            Some(net::Status::Connected(peer)) => format!("Connected to {peer}  "),
+            Some(net::Status::Unresponsive) => "Opponent not responding - x: abort  ".to_string(),
+            Some(net::Status::Disconnected) => "Opponent disconnected  ".to_string(),
+        } + &self.practice_status();
+        let selection = self
+            .selected_lane()
+            .and_then(|lane| self.game.landing(lane))
+            .map(|(row, column)| format!("  col {}, row {}", column + 1, row + 1))
+            .unwrap_or_default();
+        let limit = match self.game.moves_left() {
+            Some(1) => "  1 move left".to_string(),
+            Some(left) => format!("  {left} moves left"),
+            None => String::new(),
+        };
+        let heights = heights_summary(
+            &self.game,
+            (width as usize).saturating_sub(
+                connection.chars().count() + selection.chars().count() + limit.chars().count(),
+            ),
+        );
+        Paragraph::new(connection + &heights + &selection + &limit)
+    }
+
+    /// Moves the field chosen without gravity one step the way the arrow
+    /// `code` points on screen, starting from the last move or the middle of
+    /// the board.
+    fn move_cursor(&mut self, code: KeyCode) {
+        let (height, width) = (self.game.height(), self.game.width());
+        let (mut row, mut column) = match (self.selected_lane(), self.game.history().last()) {
+            (Some(lane), _) => (lane / width, lane % width),
+            (None, Some(m)) => (m.row, m.column),
+            (None, None) => {
+                let lane = self.game.rules().lane(height / 2, width / 2);
+                self.select(Some(lane));
+                return;
+            }
+        };
+        let across = self.mirrored != self.rotated;
+        match code {
+            KeyCode::Up if self.rotated => row = row.saturating_sub(1),
+            KeyCode::Down if !self.rotated => row = row.saturating_sub(1),
+            KeyCode::Up | KeyCode::Down => row = (row + 1).min(height - 1),
+            KeyCode::Left if !across => column = column.saturating_sub(1),
+            KeyCode::Right if across => column = column.saturating_sub(1),
+            _ => column = (column + 1).min(width - 1),
+        }
+        self.select(Some(self.game.rules().lane(row, column)));
+    }
+
+    /// Replaces the input with the 1-based number of `lane`.
+    fn select(&mut self, lane: Option<usize>) {
+        if let Some(lane) = lane {
+            self.input = (lane + 1).to_string();
+        }
+    }
 
-        frame.render_widget(self.board_canvas(), main_layout[0]);
-        frame.render_widget(self.red_player_canvas(), controls_layout[0]);
-        frame.render_widget(self.blue_player_canvas(), controls_layout[1]);
+    /// The 0-based lane currently typed in, if it is on the board.
+    fn selected_lane(&self) -> Option<usize> {
+        self.game.parse_lane(&self.input).ok()
     }
 
     fn red_player_canvas(&self) -> impl Widget + '_ {
-        let (color, text) = match self.turn {
-            Turn::Red => (Color::Red, self.input.as_str()),
-            Turn::Blue => (Color::White, ""),
+        self.player_canvas(Turn::Red, "Red player")
+    }
+
+    fn blue_player_canvas(&self) -> impl Widget + '_ {
+        self.player_canvas(Turn::Blue, "Blue player")
+    }
+
+    fn player_canvas(&self, player: Turn, title: &'static str) -> impl Widget + '_ {
+        let highlight = Style::default().fg(self.theme.highlight);
+        let (color, text) = if self.game.turn() != player {
+            (self.theme.idle, text::Line::default())
+        } else if let Some(thinking) = &self.thinking {
+            (
+                self.theme.player(player),
+                text::Line::styled(format!("thinking... depth {}", thinking.depth), highlight),
+            )
+        } else {
+            (self.theme.player(player), self.input_line(highlight))
+        };
+        let mut summary = format!(
+            "discs: {}  threats: {}  open lines: {}",
+            self.game.disc_count(player),
+            self.game.threats(player).len(),
+            self.game.open_lines(player)
+        );
+        if self.game.rules().power_pieces > 0 {
+            summary += &format!(
+                "  bombs: {}  anvils: {}",
+                self.game.pieces_left(player, MoveKind::Bomb),
+                self.game.pieces_left(player, MoveKind::Anvil)
+            );
+        }
+        let mut lines = vec![text, text::Line::raw(summary)];
+        if let Some(hint) = self.win_hint(player) {
+            lines.push(text::Line::styled(hint, highlight.italic()));
+        }
+        let title = match &self.clocks {
+            Some(clocks) => format!("{title} {}", clocks.describe(player)),
+            None => title.to_string(),
+        };
+        // Without color the symbol names the player and a double border
+        // marks whose turn it is.
+        let (title, border) = if !self.theme.symbols {
+            (title, BorderType::Plain)
+        } else if self.game.turn() == player && !self.game.is_over() {
+            let title = format!("{title} ({}) to move", self.theme.disc(player));
+            (title, BorderType::Double)
+        } else {
+            let title = format!("{title} ({})", self.theme.disc(player));
+            (title, BorderType::Plain)
         };
-        Paragraph::new(text)
+        let score = self.score.with(&self.game);
+        let score = match score.first_to() {
+            _ if score.games() == 0 && score.first_to().is_none() => String::new(),
+            Some(wins) => format!("{}  first to {wins}", score.describe(player)),
+            None => score.describe(player),
+        };
+        Paragraph::new(lines)
             .block(
                 Block::default()
                     .borders(Borders::ALL)
-                    .title("Red player")
+                    .border_type(border)
+                    .title(title)
+                    .title(
+                        block::Title::from(score)
+                            .position(block::Position::Bottom)
+                            .alignment(Alignment::Right),
+                    )
                     .border_style(Style::default().fg(color)),
             )
             .alignment(Alignment::Center)
     }
 
-    fn blue_player_canvas(&self) -> impl Widget + '_ {
-        let (color, text) = match self.turn {
-            Turn::Blue => (Color::Blue, self.input.as_str()),
-            Turn::Red => (Color::White, ""),
+    /// The analysis readout for `game`, when analysis mode is on: the score
+    /// for Red and the first moves of the expected line.
+    fn evaluation_text(&self, game: &Game) -> String {
+        if !self.analysis {
+            return String::new();
+        }
+        let analysis = match &self.evaluation {
+            Some((position, analysis)) if *position == position_key(game) => analysis,
+            _ => return " analysing... ".to_string(),
+        };
+        let Some(analysis) = analysis else {
+            return String::new();
+        };
+        let line: Vec<String> = analysis
+            .line
+            .iter()
+            .map(|lane| (lane + 1).to_string())
+            .collect();
+        format!(
+            " eval (Red) {}  best line: {} ",
+            ai::format_eval(analysis.eval),
+            line.join(" ")
+        )
+    }
+
+    /// Tells a local `player` to move that they can win right now, naming the
+    /// lane only with [`Assist::Reveal`].
+    fn win_hint(&self, player: Turn) -> Option<String> {
+        if self.assist == Assist::Off || self.game.turn() != player || !self.is_local_turn() {
+            return None;
+        }
+        let lane = *self.game.winning_drops(player).first()?;
+        Some(match self.assist {
+            Assist::Reveal => format!("You can win in {}!", self.game.lane_label(lane)),
+            _ => "You can win!".to_string(),
+        })
+    }
+
+    /// The pending input in bold with a caret, in the warning color and with
+    /// the reason when it cannot be played, or else with where it lands.
+    fn input_line(&self, style: Style) -> text::Line<'_> {
+        let free = self.game.rules().gravity.is_free();
+        // Pops and power pieces are tried out on a copy of the game.
+        let (kind, column) = MoveKind::split(&self.input);
+        let special = (kind != MoveKind::Drop && !column.is_empty()).then(|| {
+            self.game.parse_lane(column).and_then(|column| {
+                let mut after = self.game.clone();
+                after.play(kind, column).map(|()| column)
+            })
+        });
+        let (problem, landing) = match self.game.parse_lane(&self.input) {
+            _ if column.is_empty() => (None, None),
+            _ if special.is_some() => (special.and_then(Result::err), None),
+            Ok(lane) => match self.game.landing(lane) {
+                Some(landing) => (None, Some(landing)),
+                None if free => (Some(game::MoveError::FieldTaken), None),
+                None => (Some(game::MoveError::ColumnFull), None),
+            },
+            Err(err) => (Some(err), None),
+        };
+        // Without gravity the input is the field under the cursor.
+        let shown = match self.selected_lane() {
+            Some(lane) if free => self.game.lane_label(lane),
+            _ => self.input.clone(),
+        };
+        let style = match problem {
+            Some(_) => style.fg(self.theme.warning),
+            None => style,
+        }
+        .add_modifier(Modifier::BOLD);
+        let mut spans = vec![
+            Span::styled(shown, style),
+            Span::styled("_", style.add_modifier(Modifier::SLOW_BLINK)),
+        ];
+        if let Some(problem) = problem {
+            spans.push(Span::styled(format!("  {problem}"), style));
+        }
+        // Where the disc would settle along the lane: a row, or a column when
+        // it slides sideways. Placed discs stay where they are chosen.
+        if let Some((row, column)) = landing {
+            let preview = match self.game.rules().gravity {
+                Gravity::Down | Gravity::Up => format!("  lands in row {}", row + 1),
+                Gravity::Left | Gravity::Right => format!("  lands in column {}", column + 1),
+                Gravity::Off => String::new(),
+            };
+            spans.push(Span::raw(preview));
+        }
+        if let Some(Ok(column)) = special {
+            let column = column + 1;
+            spans.push(Span::raw(match kind {
+                MoveKind::Pop => format!("  pops your disc out of column {column}"),
+                MoveKind::Anvil => format!("  anvil clears column {column}"),
+                _ => format!("  bomb clears the top of column {column}"),
+            }));
+        }
+        text::Line::from(spans)
+    }
+
+    /// Where `column` is drawn, counting from the left; fractional for discs
+    /// on their way. Mirroring only changes the picture: columns keep their
+    /// numbers, so column 1 is drawn rightmost. Rotating mirrors as well,
+    /// so both together only turn the board upside down.
+    fn display_column(&self, column: f64, width: usize) -> f64 {
+        if self.mirrored != self.rotated {
+            (width - 1) as f64 - column
+        } else {
+            column
+        }
+    }
+
+    /// Where `row` is drawn, counting from the bottom, as for
+    /// [`App::display_column`]: a rotated board has row 1 at the top.
+    fn display_row(&self, row: f64, height: usize) -> f64 {
+        if self.rotated {
+            (height - 1) as f64 - row
+        } else {
+            row
+        }
+    }
+
+    /// Draws `game` with [`App::board_canvas`], or with [`App::mini_board`]
+    /// when `area` is too small for the canvas.
+    fn render_board(
+        &self,
+        frame: &mut Frame,
+        game: &Game,
+        marker: Option<usize>,
+        falling: Option<animation::Fall>,
+        shift: (f64, f64),
+        area: Rect,
+    ) {
+        let (columns, rows) = CANVAS_FIELD;
+        let needed = |fields: usize, cells: u16| (fields as u16).saturating_mul(cells) + 2;
+        if area.width >= needed(game.width(), columns) && area.height >= needed(game.height(), rows)
+        {
+            frame.render_widget(self.board_canvas(game, marker, falling, shift, area), area);
+        } else {
+            frame.render_widget(self.mini_board(game, area), area);
+        }
+    }
+
+    /// "4 in a row", followed by the winning drops of each player when
+    /// counted.
+    fn board_title(&self, game: &Game) -> String {
+        let mut title = format!("{} in a row", game.rules().connect);
+        if game.rules().gravity.is_free() {
+            title += ", free placement";
+        }
+        if game.rules().pop_out {
+            title += ", Pop Out";
+        }
+        if game.rules().power_pieces > 0 {
+            title += ", power pieces";
+        }
+        if self.threat_count {
+            let [red, blue] =
+                [Turn::Red, Turn::Blue].map(|player| game.winning_drops(player).len());
+            title += &format!("  threats {}:{red} {}:{blue}", Turn::Red, Turn::Blue);
+        }
+        title
+    }
+
+    /// Draws `game` with one character per field, for areas too small for
+    /// [`App::board_canvas`]. Fields are spaced out when `area` leaves room,
+    /// and when it is too short the top rows are left out first.
+    fn mini_board(&self, game: &Game, area: Rect) -> impl Widget + '_ {
+        let (width, height) = (game.width(), game.height());
+        let spaced = area.width.saturating_sub(2) as usize >= 2 * width - 1;
+        let rows = height.min(area.height.saturating_sub(2) as usize);
+        let order: Vec<usize> = if self.rotated {
+            (0..rows).collect()
+        } else {
+            (0..rows).rev().collect()
         };
-        Paragraph::new(text)
+        let lines: Vec<text::Line> = order
+            .into_iter()
+            .map(|row| {
+                let mut spans = Vec::new();
+                for shown in 0..width {
+                    if spaced && shown > 0 {
+                        spans.push(Span::raw(" "));
+                    }
+                    let column = self.display_column(shown as f64, width) as usize;
+                    spans.push(match game.field(row, column) {
+                        Field::Empty => Span::styled("·", Style::default().fg(self.theme.grid)),
+                        Field::Blocked => Span::styled("#", Style::default().fg(self.theme.idle)),
+                        Field::Red => Span::styled(
+                            self.theme.disc(Turn::Red),
+                            Style::default().fg(self.theme.player1),
+                        ),
+                        Field::Blue => Span::styled(
+                            self.theme.disc(Turn::Blue),
+                            Style::default().fg(self.theme.player2),
+                        ),
+                    });
+                }
+                text::Line::from(spans)
+            })
+            .collect();
+        Paragraph::new(lines)
+            .alignment(Alignment::Center)
+            .style(Style::default().bg(self.theme.background))
             .block(
                 Block::default()
                     .borders(Borders::ALL)
-                    .title("Blue player")
-                    .border_style(Style::default().fg(color)),
+                    .title(self.board_title(game))
+                    .title(
+                        block::Title::from(self.message.as_deref().unwrap_or_default())
+                            .position(block::Position::Bottom),
+                    )
+                    .border_style(Style::default().fg(self.theme.grid)),
             )
-            .alignment(Alignment::Center)
     }
 
-    fn board_canvas(&self) -> impl Widget + '_ {
+    /// Draws `game`, pointing at lane `marker` with an arrow on its landing
+    /// field. A `falling` disc is drawn on its way rather than on its field,
+    /// and everything is moved by `shift` rows and columns while draining.
+    /// The board is fitted into `area`.
+    fn board_canvas<'a>(
+        &'a self,
+        game: &'a Game,
+        marker: Option<usize>,
+        falling: Option<animation::Fall>,
+        shift: (f64, f64),
+        area: Rect,
+    ) -> impl Widget + 'a {
+        let frame = self.frame;
+        // Inside the border.
+        let (x_bounds, y_bounds) = self.geometry.fitted_bounds(
+            game.width(),
+            game.height(),
+            area.width.saturating_sub(2),
+            area.height.saturating_sub(2),
+        );
+        let threats = self
+            .analysis
+            .then(|| [Turn::Red, Turn::Blue].map(|player| (player, game.winning_drops(player))));
+        let double_threats: Vec<String> = threats
+            .iter()
+            .flatten()
+            .filter(|(_, columns)| columns.len() > 1)
+            .map(|(player, _)| format!(" {} has a double threat!", player.name()))
+            .collect();
         Canvas::default()
-            .block(Block::default().borders(Borders::ALL).title("4 in a row"))
-            .paint(|ctx| {
-                for (i, row) in self.board.iter().enumerate() {
-                    for (j, field) in row.iter().enumerate() {
-                        match field {
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(self.board_title(game))
+                    .title(double_threats.concat())
+                    .title(
+                        block::Title::from(self.evaluation_text(game)).alignment(Alignment::Right),
+                    )
+                    .title(
+                        block::Title::from(self.message.as_deref().unwrap_or_default())
+                            .position(block::Position::Bottom),
+                    )
+                    .border_style(Style::default().fg(self.theme.grid)),
+            )
+            .background_color(self.theme.background)
+            .paint(move |ctx| {
+                let geometry = self.geometry;
+                let disc = geometry.disc;
+                let shown = |row: f64, column: f64| {
+                    (
+                        self.display_row(row + shift.0, game.height()),
+                        self.display_column(column + shift.1, game.width()),
+                    )
+                };
+                let corner = |row: f64, column: f64| {
+                    let (row, column) = shown(row, column);
+                    geometry.corner(row, column)
+                };
+                let label = |row: f64, column: f64, offset: f64| {
+                    let (row, column) = shown(row, column);
+                    geometry.label(row, column, offset)
+                };
+                let square = |(x, y): (f64, f64), color: Color| Rectangle {
+                    x,
+                    y,
+                    width: disc,
+                    height: disc,
+                    color,
+                };
+                let cross = |(x, y): (f64, f64), color: Color| {
+                    [(y, y + disc), (y + disc, y)]
+                        .map(|(y1, y2)| canvas::Line::new(x, y1, x + disc, y2, color))
+                };
+                // Without color Red is drawn as a cross and Blue as a square.
+                let draw_disc = |ctx: &mut canvas::Context, corner: (f64, f64), player: Turn| {
+                    let color = self.theme.player(player);
+                    match player {
+                        Turn::Red if self.theme.symbols => {
+                            for line in cross(corner, color) {
+                                ctx.draw(&line);
+                            }
+                        }
+                        _ => ctx.draw(&square(corner, color)),
+                    }
+                };
+                // A flashing winning line is hidden every other period.
+                let dark = self.celebrate.flashes()
+                    && !(frame / animation::FLASH_FRAMES).is_multiple_of(2);
+                let hidden = dark
+                    .then(|| game.winning_line())
+                    .flatten()
+                    .unwrap_or_default();
+                for i in 0..game.height() {
+                    for j in 0..game.width() {
+                        if falling.is_some_and(|fall| fall.target() == (i, j))
+                            || hidden.contains(&(i, j))
+                        {
+                            continue;
+                        }
+                        let (row, column) = (i as f64, j as f64);
+                        match game.field(i, j) {
                             Field::Empty => {}
-                            Field::Blue => {
-                                ctx.draw(&Rectangle {
-                                    x: (j as f64) * 50.0,
-                                    y: (i as f64) * 50.0,
-                                    width: 40.0,
-                                    height: 40.0,
-                                    color: Color::Blue,
-                                });
+                            Field::Blocked => {
+                                let color = self.theme.idle;
+                                ctx.draw(&square(corner(row, column), color));
+                                for line in cross(corner(row, column), color) {
+                                    ctx.draw(&line);
+                                }
                             }
-                            Field::Red => {
-                                ctx.draw(&Rectangle {
-                                    x: (j as f64) * 50.0,
-                                    y: (i as f64) * 50.0,
-                                    width: 40.0,
-                                    height: 40.0,
-                                    color: Color::Red,
-                                });
+                            Field::Blue => draw_disc(ctx, corner(row, column), Turn::Blue),
+                            Field::Red => draw_disc(ctx, corner(row, column), Turn::Red),
+                        }
+                    }
+                }
+                if let Some(fall) = falling {
+                    let (row, column) = fall.position(frame);
+                    let (target_row, target_column) = fall.target();
+                    let player = match game.field(target_row, target_column) {
+                        Field::Blue => Turn::Blue,
+                        _ => Turn::Red,
+                    };
+                    draw_disc(ctx, corner(row, column), player);
+                }
+                // Canvas units per terminal cell, to center longer labels.
+                let cell = (x_bounds[1] - x_bounds[0]) / f64::from(area.width.max(3) - 2);
+                let centered = |row: usize, column: usize, text: &str| {
+                    let (x, y) = label(row as f64, column as f64, 0.0);
+                    (x - cell * (text.chars().count() - 1) as f64 / 2.0, y)
+                };
+                if self.coordinates {
+                    let style = Style::default().fg(self.theme.idle);
+                    for i in 0..game.height() {
+                        for j in 0..game.width() {
+                            let index = format!("{i},{j}");
+                            let (x, y) = centered(i, j, &index);
+                            ctx.print(x, y, text::Line::styled(index, style));
+                        }
+                    }
+                }
+                if self.move_numbers {
+                    for (number, m) in game.history().iter().enumerate() {
+                        // Popped and cleared discs left the board.
+                        if game.field(m.row, m.column) != m.player.into()
+                            || falling.is_some_and(|fall| fall.target() == (m.row, m.column))
+                            || hidden.contains(&(m.row, m.column))
+                        {
+                            continue;
+                        }
+                        // Cut out of the disc, so it reads on either color.
+                        let style = Style::default()
+                            .fg(self.theme.background)
+                            .bg(self.theme.player(m.player))
+                            .bold();
+                        let number = (number + 1).to_string();
+                        let (x, y) = centered(m.row, m.column, &number);
+                        ctx.print(x, y, text::Line::styled(number, style));
+                    }
+                }
+                let trail = if self.move_numbers { 0 } else { self.trail };
+                let recent = game
+                    .history()
+                    .iter()
+                    .rev()
+                    .filter(|m| game.field(m.row, m.column) == m.player.into())
+                    .take(trail);
+                let line = game.winning_line().unwrap_or_default();
+                for (age, m) in recent.enumerate() {
+                    // The winning line has its own highlight, dark or lit.
+                    if falling.is_some_and(|fall| fall.target() == (m.row, m.column))
+                        || line.contains(&(m.row, m.column))
+                    {
+                        continue;
+                    }
+                    let symbols = ["●", "•", "∙"];
+                    let mut style = Style::default().fg(self.theme.player(m.player));
+                    if age == 0 {
+                        style = style.bold();
+                    } else if age >= symbols.len() {
+                        style = style.dim();
+                    }
+                    let (x, y) = label(m.row as f64, m.column as f64, 0.0);
+                    ctx.print(
+                        x,
+                        y,
+                        text::Line::styled(symbols[age.min(symbols.len() - 1)], style),
+                    );
+                }
+                for (player, lanes) in threats.iter().flatten() {
+                    // Red's marker sits left of Blue's, so a column winning
+                    // for both shows both.
+                    let offset = match player {
+                        Turn::Red => -0.25,
+                        Turn::Blue => 0.15,
+                    };
+                    for &lane in lanes {
+                        let Some((row, column)) = game.landing(lane) else {
+                            continue;
+                        };
+                        let (x, y) = label(row as f64, column as f64, offset);
+                        ctx.print(
+                            x,
+                            y,
+                            text::Line::styled(
+                                "!",
+                                Style::default().fg(self.theme.player(*player)).bold(),
+                            ),
+                        );
+                    }
+                }
+                // The lanes the mover wins in sit inside those to block, so a
+                // lane that is both shows both.
+                let outlines = [
+                    (game.turn(), self.theme.player(game.turn()), 0.3),
+                    (game.turn().other(), self.theme.warning, 0.45),
+                ];
+                let outlined = self.threat_outlines && !game.is_over();
+                for (player, color, pad) in outlines.into_iter().filter(|_| outlined) {
+                    let pad = pad * (geometry::SPACING - disc);
+                    for lane in game.winning_drops(player) {
+                        let fields = (0..game.height())
+                            .flat_map(|i| (0..game.width()).map(move |j| (i, j)))
+                            .filter(|&(i, j)| game.rules().lane(i, j) == lane);
+                        // The bottom left and top right corners of the lane.
+                        let (mut low, mut high) = ((f64::MAX, f64::MAX), (f64::MIN, f64::MIN));
+                        for (i, j) in fields {
+                            let (x, y) = corner(i as f64, j as f64);
+                            low = (low.0.min(x), low.1.min(y));
+                            high = (high.0.max(x + disc), high.1.max(y + disc));
+                        }
+                        ctx.draw(&Rectangle {
+                            x: low.0 - pad,
+                            y: low.1 - pad,
+                            width: high.0 - low.0 + 2.0 * pad,
+                            height: high.1 - low.1 + 2.0 * pad,
+                            color,
+                        });
+                    }
+                }
+                let hinted = self
+                    .hint
+                    .as_ref()
+                    .filter(|(position, _, _)| *position == position_key(game));
+                if let Some(&(_, lane, _)) = hinted {
+                    let style = Style::default().fg(self.theme.highlight).bold();
+                    for i in 0..game.height() {
+                        for j in 0..game.width() {
+                            if game.rules().lane(i, j) == lane && game.field(i, j) == Field::Empty {
+                                let (x, y) = label(i as f64, j as f64, 0.0);
+                                ctx.print(x, y, text::Line::styled("?", style));
                             }
                         }
                     }
                 }
+                if let Some(fireworks) = self.fireworks {
+                    let (width, height) =
+                        geometry.corner(game.height() as f64, game.width() as f64);
+                    let sparks = fireworks.sparks(frame, width, height);
+                    let colors = [self.theme.player1, self.theme.player2];
+                    for (burst, color) in colors.into_iter().enumerate() {
+                        let coords: Vec<(f64, f64)> = sparks
+                            .iter()
+                            .filter(|(_, _, spark_burst)| spark_burst % colors.len() == burst)
+                            .map(|&(x, y, _)| (x, y))
+                            .collect();
+                        ctx.draw(&Points {
+                            coords: &coords,
+                            color,
+                        });
+                    }
+                }
+                let marker = marker.filter(|_| !game.is_over());
+                if let Some(lane) = marker {
+                    // Pointing the way discs are drawn to fall.
+                    let flipped = |gravity: Gravity| match gravity {
+                        Gravity::Down => Gravity::Up,
+                        Gravity::Up => Gravity::Down,
+                        Gravity::Left => Gravity::Right,
+                        Gravity::Right => Gravity::Left,
+                        Gravity::Off => Gravity::Off,
+                    };
+                    let gravity = game.rules().gravity;
+                    let drawn = match gravity {
+                        Gravity::Down | Gravity::Up if self.rotated => flipped(gravity),
+                        Gravity::Left | Gravity::Right if self.mirrored != self.rotated => {
+                            flipped(gravity)
+                        }
+                        _ => gravity,
+                    };
+                    let arrow = match drawn {
+                        Gravity::Down => "▼",
+                        Gravity::Up => "▲",
+                        Gravity::Left => "◀",
+                        Gravity::Right => "▶",
+                        Gravity::Off => "◆",
+                    };
+                    let style = Style::default().fg(self.theme.player(game.turn()));
+                    // One arrow just outside the board where the disc enters...
+                    let (last_row, last_column) =
+                        ((game.height() - 1) as f64, (game.width() - 1) as f64);
+                    let entry = match gravity {
+                        Gravity::Down => Some((last_row + 0.6, lane as f64)),
+                        Gravity::Up => Some((-0.6, lane as f64)),
+                        Gravity::Left => Some((lane as f64, last_column + 0.6)),
+                        Gravity::Right => Some((lane as f64, -0.6)),
+                        Gravity::Off => None,
+                    };
+                    if let Some((row, column)) = entry {
+                        let (x, y) = label(row, column, 0.0);
+                        ctx.print(x, y, text::Line::styled(arrow, style));
+                    }
+                    // ...and one on the field it lands on, or without gravity
+                    // on the chosen field, even when it is taken.
+                    let field = if gravity.is_free() {
+                        Some((lane / game.width(), lane % game.width()))
+                    } else {
+                        game.landing(lane)
+                    };
+                    if let Some((row, column)) = field {
+                        let (x, y) = label(row as f64, column as f64, 0.0);
+                        ctx.print(x, y, text::Line::styled(arrow, style));
+                    }
+                }
             })
-            .x_bounds([0.0, 350.0])
-            .y_bounds([0.0, 300.0])
-            .marker(Marker::HalfBlock)
+            .x_bounds(x_bounds)
+            .y_bounds(y_bounds)
+            .marker(self.marker)
+    }
+}
+
+/// Number of discs in each column, e.g. `[3 0 2 1 0 4 2]`, cut short with `…`
+/// to fit in `max_width` characters.
+fn heights_summary(game: &Game, max_width: usize) -> String {
+    let heights: Vec<String> = (0..game.width())
+        .map(|column| game.column_height(column).to_string())
+        .collect();
+    let full = format!("[{}]", heights.join(" "));
+    if full.chars().count() <= max_width {
+        return full;
     }
+    let mut short: String = full.chars().take(max_width.saturating_sub(2)).collect();
+    short.push_str("…]");
+    short
+}
+
+/// Identifies a position for the analysis and hint caches: the rules and
+/// the [`Game::hash`] of the fields and who is to move.
+type PositionKey = (game::Rules, u64);
+
+fn position_key(game: &Game) -> PositionKey {
+    (game.rules(), game.hash())
 }
 
-fn init_terminal() -> io::Result<Terminal<CrosstermBackend<Stdout>>> {
+/// A `width` x `height` rectangle centered in `area`, clipped to fit.
+fn centered_rect(width: u16, height: u16, area: Rect) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+    Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    )
+}
+
+/// Sets up the terminal, drawing into `inline` lines below the prompt when
+/// given rather than the whole screen.
+fn init_terminal(
+    alternate_screen: bool,
+    inline: Option<u16>,
+) -> io::Result<Terminal<CrosstermBackend<Stdout>>> {
     enable_raw_mode()?;
-    stdout().execute(EnterAlternateScreen)?;
-    Terminal::new(CrosstermBackend::new(stdout()))
+    if alternate_screen {
+        stdout().execute(EnterAlternateScreen)?;
+    }
+    stdout().execute(EnableBracketedPaste)?;
+    let viewport = match inline {
+        Some(height) => Viewport::Inline(height),
+        None => Viewport::Fullscreen,
+    };
+    Terminal::with_options(
+        CrosstermBackend::new(stdout()),
+        TerminalOptions { viewport },
+    )
 }
 
-fn restore_terminal() -> io::Result<()> {
+fn restore_terminal(alternate_screen: bool) -> io::Result<()> {
     disable_raw_mode()?;
-    stdout().execute(LeaveAlternateScreen)?;
+    stdout().execute(DisableBracketedPaste)?;
+    if alternate_screen {
+        stdout().execute(LeaveAlternateScreen)?;
+    }
     Ok(())
 }
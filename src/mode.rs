@@ -0,0 +1,45 @@
+//! What a game allows depending on who plays in it.
+
+/// Who the local player faces.
+#[derive(Copy, Clone, PartialEq)]
+pub enum Mode {
+    /// Both players share this terminal.
+    Local,
+    Computer,
+    /// Moves are also played on the opponent's side, so both boards have
+    /// to stay the same.
+    Network,
+    /// The computer plays both sides while the local player watches.
+    Exhibition,
+}
+
+impl Mode {
+    pub fn new(networked: bool, computer: bool) -> Mode {
+        match (networked, computer) {
+            (true, _) => Mode::Network,
+            (false, true) => Mode::Computer,
+            (false, false) => Mode::Local,
+        }
+    }
+
+    /// Whether moves may be taken back. A network game would desync, since
+    /// the opponent's board keeps the move, and in an exhibition nobody
+    /// here made one.
+    pub fn allows_undo(self) -> bool {
+        matches!(self, Mode::Local | Mode::Computer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn undo_is_refused_over_the_network_and_in_exhibitions() {
+        assert!(Mode::new(false, false).allows_undo());
+        assert!(Mode::new(false, true).allows_undo());
+        assert!(!Mode::new(true, false).allows_undo());
+        assert!(Mode::new(true, true) == Mode::Network);
+        assert!(!Mode::Exhibition.allows_undo());
+    }
+}
@@ -0,0 +1,254 @@
+//! Playing against another instance over TCP.
+//!
+//! The host plays Red and the joining player Blue. Each move is sent as a
+//! line `move <lane>` with the 0-based lane, chat messages as
+//! `chat <text>` and giving up as `resign`. Both sides send `ping` every [`HEARTBEAT`], so a peer
+//! that stops responding without closing the socket is noticed.
+
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    net::{Shutdown, SocketAddr, TcpListener, TcpStream},
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// How often an idle connection sends `ping`.
+pub const HEARTBEAT: Duration = Duration::from_secs(2);
+/// Silence after which the opponent counts as not responding.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Something that happened on the connection, see [`Connection::poll`].
+#[derive(PartialEq)]
+pub enum NetEvent {
+    Connected,
+    Move(usize),
+    Chat(String),
+    Resigned,
+    Disconnected,
+}
+
+/// What the opponent's side of the game looks like from here.
+#[derive(Copy, Clone, PartialEq)]
+pub enum Status {
+    Waiting,
+    Connected(SocketAddr),
+    /// Connected, but nothing arrived for a while.
+    Unresponsive,
+    Disconnected,
+}
+
+/// A connection to the opponent, established on a background thread.
+pub struct Connection {
+    events: Receiver<Event>,
+    stream: Option<TcpStream>,
+    status: Status,
+    peer: Option<SocketAddr>,
+    last_sent: Instant,
+    last_received: Instant,
+    timeout: Duration,
+}
+
+/// Messages from the background thread: the stream for sending once
+/// connected, then whatever the peer sends.
+enum Event {
+    Connected(TcpStream, SocketAddr),
+    Line(String),
+    Closed,
+}
+
+impl Connection {
+    /// Waits for an opponent on `port`.
+    pub fn host(port: u16) -> io::Result<Connection> {
+        let listener = TcpListener::bind(("0.0.0.0", port))?;
+        Ok(Connection::spawn(move || {
+            listener.accept().map(|(stream, _)| stream)
+        }))
+    }
+
+    /// Connects to an opponent hosting at `address`.
+    pub fn join(address: String) -> Connection {
+        Connection::spawn(move || TcpStream::connect(address))
+    }
+
+    fn spawn(connect: impl FnOnce() -> io::Result<TcpStream> + Send + 'static) -> Connection {
+        let (sender, events) = mpsc::channel();
+        thread::spawn(move || {
+            let _ = read(connect(), &sender);
+            let _ = sender.send(Event::Closed);
+        });
+        Connection {
+            events,
+            stream: None,
+            status: Status::Waiting,
+            peer: None,
+            last_sent: Instant::now(),
+            last_received: Instant::now(),
+            timeout: TIMEOUT,
+        }
+    }
+
+    pub fn status(&self) -> Status {
+        self.status
+    }
+
+    /// Tells the opponent about a local move.
+    pub fn send_move(&mut self, lane: usize) {
+        self.send(&format!("move {lane}"));
+    }
+
+    /// Tells the opponent the local player gave up.
+    pub fn send_resign(&mut self) {
+        self.send("resign");
+    }
+
+    /// Sends a chat message, which must fit on one line.
+    pub fn send_chat(&mut self, text: &str) {
+        self.send(&format!("chat {text}"));
+    }
+
+    fn send(&mut self, line: &str) {
+        let sent = self
+            .stream
+            .as_mut()
+            .is_some_and(|stream| writeln!(stream, "{line}").is_ok());
+        self.last_sent = Instant::now();
+        if !sent && self.status != Status::Waiting {
+            self.status = Status::Disconnected;
+        }
+    }
+
+    /// The events that arrived since the last call. Also sends the
+    /// heartbeat, so it has to be called regularly.
+    pub fn poll(&mut self) -> Vec<NetEvent> {
+        let mut events = Vec::new();
+        while let Ok(event) = self.events.try_recv() {
+            match event {
+                Event::Connected(stream, peer) => {
+                    self.stream = Some(stream);
+                    self.peer = Some(peer);
+                    self.last_received = Instant::now();
+                    self.status = Status::Connected(peer);
+                    events.push(NetEvent::Connected);
+                }
+                Event::Line(line) => {
+                    self.last_received = Instant::now();
+                    if let (Status::Unresponsive, Some(peer)) = (self.status, self.peer) {
+                        self.status = Status::Connected(peer);
+                    }
+                    let lane = line
+                        .strip_prefix("move ")
+                        .and_then(|lane| lane.parse().ok());
+                    if let Some(lane) = lane {
+                        events.push(NetEvent::Move(lane));
+                    } else if let Some(text) = line.strip_prefix("chat ") {
+                        events.push(NetEvent::Chat(text.to_string()));
+                    } else if line == "resign" {
+                        events.push(NetEvent::Resigned);
+                    }
+                }
+                Event::Closed => {
+                    self.stream = None;
+                    self.status = Status::Disconnected;
+                    events.push(NetEvent::Disconnected);
+                }
+            }
+        }
+        if matches!(self.status, Status::Connected(_) | Status::Unresponsive) {
+            if self.last_sent.elapsed() >= HEARTBEAT {
+                self.send("ping");
+            }
+            if self.last_received.elapsed() >= self.timeout {
+                self.status = Status::Unresponsive;
+            }
+        }
+        events
+    }
+}
+
+impl Drop for Connection {
+    /// Closes the socket so the reading thread and the opponent notice.
+    fn drop(&mut self) {
+        if let Some(stream) = &self.stream {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+    }
+}
+
+/// Hands the connected stream to the UI thread and forwards incoming lines.
+fn read(stream: io::Result<TcpStream>, sender: &Sender<Event>) -> io::Result<()> {
+    let stream = stream?;
+    let peer = stream.peer_addr()?;
+    sender
+        .send(Event::Connected(stream.try_clone()?, peer))
+        .map_err(|_| io::ErrorKind::BrokenPipe)?;
+    for line in BufReader::new(stream).lines() {
+        sender
+            .send(Event::Line(line?))
+            .map_err(|_| io::ErrorKind::BrokenPipe)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wait_for(connection: &mut Connection, expected: NetEvent) -> bool {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while Instant::now() < deadline {
+            if connection.poll().contains(&expected) {
+                return true;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        false
+    }
+
+    #[test]
+    fn silent_peer_becomes_unresponsive() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let mut host = Connection::spawn(move || listener.accept().map(|(stream, _)| stream));
+        host.timeout = Duration::from_millis(50);
+        let peer = TcpStream::connect(address).unwrap();
+        assert!(wait_for(&mut host, NetEvent::Connected));
+
+        thread::sleep(Duration::from_millis(100));
+        host.poll();
+        assert!(host.status() == Status::Unresponsive);
+
+        host.timeout = Duration::from_secs(5);
+        writeln!(&peer, "ping").unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while host.status() == Status::Unresponsive && Instant::now() < deadline {
+            host.poll();
+        }
+        assert!(matches!(host.status(), Status::Connected(_)));
+    }
+
+    #[test]
+    fn moves_reach_the_peer_and_disconnects_are_noticed() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let mut host = Connection::spawn(move || listener.accept().map(|(stream, _)| stream));
+        let mut guest = Connection::join(address.to_string());
+        assert!(host.status() == Status::Waiting);
+
+        assert!(wait_for(&mut guest, NetEvent::Connected));
+        assert!(wait_for(&mut host, NetEvent::Connected));
+        guest.send_move(3);
+        assert!(wait_for(&mut host, NetEvent::Move(3)));
+        host.send_chat("good luck");
+        assert!(wait_for(
+            &mut guest,
+            NetEvent::Chat("good luck".to_string())
+        ));
+        guest.send_resign();
+        assert!(wait_for(&mut host, NetEvent::Resigned));
+
+        drop(guest);
+        assert!(wait_for(&mut host, NetEvent::Disconnected));
+        assert!(host.status() == Status::Disconnected);
+    }
+}
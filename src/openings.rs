@@ -0,0 +1,74 @@
+//! Named openings to start a game from, for practicing a structure.
+
+use connect4::{
+    game::Game,
+    headless::{self, ScriptError},
+};
+
+pub struct Opening {
+    pub name: &'static str,
+    /// The moves from the empty board, one column number per move.
+    pub moves: &'static str,
+    pub text: &'static str,
+}
+
+impl Opening {
+    /// `game` with the opening's moves played, or the first one that does
+    /// not fit its rules.
+    pub fn play(&self, mut game: Game) -> Result<Game, ScriptError> {
+        for (index, lane) in headless::parse_moves(self.moves, &game)?
+            .into_iter()
+            .enumerate()
+        {
+            game.drop(lane).map_err(|error| ScriptError {
+                position: index + 1,
+                token: (lane + 1).to_string(),
+                error,
+            })?;
+        }
+        Ok(game)
+    }
+}
+
+pub const OPENINGS: &[Opening] = &[
+    Opening {
+        name: "Center start",
+        moves: "4",
+        text: "Red takes the middle column, which is part of the most lines.",
+    },
+    Opening {
+        name: "Stacked center",
+        moves: "4444",
+        text: "Both players fight over the middle column before going anywhere else.",
+    },
+    Opening {
+        name: "Open three",
+        moves: "4433",
+        text: "Blue did not block the bottom row: Red can make three in a row there, open at both ends.",
+    },
+    Opening {
+        name: "Diagonal trap",
+        moves: "4556676",
+        text: "Red has three on a diagonal and waits for column 7 to fill up to the fourth field.",
+    },
+    Opening {
+        name: "Edge start",
+        moves: "1",
+        text: "Red opens on the edge, the weakest first move. Practice punishing it as Blue.",
+    },
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_opening_leaves_a_game_to_play() {
+        for opening in OPENINGS {
+            let game = opening
+                .play(Game::new())
+                .unwrap_or_else(|err| panic!("{}: {err}", opening.name));
+            assert!(!game.is_over(), "{}", opening.name);
+        }
+    }
+}
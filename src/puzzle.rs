@@ -0,0 +1,80 @@
+//! Positions with a known best move for the player to find.
+//!
+//! A puzzle file holds a position as read by [`Game::from_board_str`] and a
+//! `solution:` line with the columns (rows with sideways gravity) that
+//! solve it, counted from 1, e.g. `solution: 4` or `solution: 3 5`.
+
+use crate::game::{Game, PersistenceError, Rules};
+
+pub struct Puzzle {
+    pub game: Game,
+    /// Lanes that solve the puzzle, 0-based.
+    pub solutions: Vec<usize>,
+}
+
+impl Puzzle {
+    pub fn parse(text: &str, rules: Rules) -> Result<Puzzle, PersistenceError> {
+        let mut board = String::new();
+        let mut solution = None;
+        for line in text.lines() {
+            match line.trim().strip_prefix("solution:") {
+                Some(lanes) => solution = Some(lanes.to_string()),
+                None => {
+                    board.push_str(line);
+                    board.push('\n');
+                }
+            }
+        }
+        let game = Game::from_board_str(&board, rules)?;
+        if game.is_over() {
+            return Err(PersistenceError::Parse(
+                "the puzzle position is already decided".to_string(),
+            ));
+        }
+        let solution = solution
+            .ok_or_else(|| PersistenceError::Parse("missing `solution:` line".to_string()))?;
+        let solutions = solution
+            .split_whitespace()
+            .map(|lane| match game.parse_lane(lane) {
+                Ok(lane) if game.landing(lane).is_some() => Ok(lane),
+                Ok(_) => Err(PersistenceError::Parse(format!(
+                    "solution `{lane}` cannot be played"
+                ))),
+                Err(err) => Err(PersistenceError::Parse(format!("solution `{lane}`: {err}"))),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if solutions.is_empty() {
+            return Err(PersistenceError::Parse(
+                "the `solution:` line names no move".to_string(),
+            ));
+        }
+        Ok(Puzzle { game, solutions })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POSITION: &str = ".......\n.......\n.......\n.......\nBB.....\nRRR....\n";
+
+    #[test]
+    fn solutions_are_read_after_the_position() {
+        let puzzle = Puzzle::parse(&format!("{POSITION}solution: 4\n"), Rules::default()).unwrap();
+        assert_eq!(puzzle.solutions, [3]);
+        assert_eq!(puzzle.game.disc_count(crate::game::Turn::Red), 3);
+
+        let puzzle = Puzzle::parse(&format!("solution: 4 1\n{POSITION}"), Rules::default());
+        assert_eq!(puzzle.map(|puzzle| puzzle.solutions).unwrap(), [3, 0]);
+    }
+
+    #[test]
+    fn unplayable_or_missing_solutions_are_rejected() {
+        let rules = Rules::default();
+        assert!(Puzzle::parse(POSITION, rules).is_err());
+        assert!(Puzzle::parse(&format!("{POSITION}solution:\n"), rules).is_err());
+        assert!(Puzzle::parse(&format!("{POSITION}solution: 8\n"), rules).is_err());
+        let full = "R......\nB......\nR......\nB......\nB......\nR......\n";
+        assert!(Puzzle::parse(&format!("{full}solution: 1\n"), rules).is_err());
+    }
+}
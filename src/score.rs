@@ -0,0 +1,140 @@
+//! The results of the games of a match, and the best-of series they may
+//! count toward.
+
+use connect4::game::{Ending, Game, Turn};
+
+#[derive(Copy, Clone, Default)]
+pub struct Score {
+    wins: [u32; 2],
+    draws: u32,
+    /// Wins that take the series, `None` when the games are only counted.
+    first_to: Option<u32>,
+}
+
+impl Score {
+    /// An empty score for a best-of-`best_of` series, or for counting games
+    /// without one.
+    pub fn new(best_of: Option<u32>) -> Score {
+        Score {
+            first_to: best_of.map(|games| games / 2 + 1),
+            ..Score::default()
+        }
+    }
+
+    /// Counts a finished game. Aborted and unfinished games do not count.
+    pub fn record(&mut self, game: &Game) {
+        if !game.is_over() || game.ending() == Some(Ending::Aborted) {
+            return;
+        }
+        match game.winner() {
+            Some(winner) => self.wins[winner as usize] += 1,
+            None => self.draws += 1,
+        }
+    }
+
+    /// Starts the count over, for the same kind of series.
+    pub fn reset(&mut self) {
+        *self = Score {
+            first_to: self.first_to,
+            ..Score::default()
+        };
+    }
+
+    /// The score with `game` counted as well.
+    pub fn with(&self, game: &Game) -> Score {
+        let mut score = *self;
+        score.record(game);
+        score
+    }
+
+    pub fn games(&self) -> u32 {
+        self.wins.iter().sum::<u32>() + self.draws
+    }
+
+    pub fn first_to(&self) -> Option<u32> {
+        self.first_to
+    }
+
+    /// The player who reached the wins needed for the series.
+    pub fn series_winner(&self) -> Option<Turn> {
+        let needed = self.first_to?;
+        [Turn::Red, Turn::Blue]
+            .into_iter()
+            .find(|&player| self.wins[player as usize] >= needed)
+    }
+
+    /// Both players' wins and the draws, e.g. `Red 2, Blue 1, draws 0`.
+    pub fn describe_match(&self) -> String {
+        format!(
+            "{} {}, {} {}, draws {}",
+            Turn::Red.name(),
+            self.wins[Turn::Red as usize],
+            Turn::Blue.name(),
+            self.wins[Turn::Blue as usize],
+            self.draws
+        )
+    }
+
+    /// `player`'s wins, losses and draws, e.g. `W 2  L 1  D 0`.
+    pub fn describe(&self, player: Turn) -> String {
+        format!(
+            "W {}  L {}  D {}",
+            self.wins[player as usize],
+            self.wins[player.other() as usize],
+            self.draws
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use connect4::game::Rules;
+
+    fn won_by(player: Turn) -> Game {
+        let mut game = Game::new();
+        game.end(Ending::Resigned(player.other()));
+        game
+    }
+
+    #[test]
+    fn counts_wins_losses_and_draws_per_player() {
+        let mut score = Score::new(None);
+        score.record(&won_by(Turn::Red));
+        score.record(&won_by(Turn::Blue));
+        score.record(&won_by(Turn::Red));
+        let mut aborted = Game::new();
+        aborted.end(Ending::Aborted);
+        score.record(&aborted);
+        score.record(&Game::new());
+        let mut drawn = Game::with_rules(Rules {
+            height: 1,
+            width: 2,
+            connect: 2,
+            ..Rules::default()
+        });
+        drawn.drop(0).unwrap();
+        drawn.drop(1).unwrap();
+        score.record(&drawn);
+        assert_eq!(score.describe(Turn::Red), "W 2  L 1  D 1");
+        assert_eq!(score.describe(Turn::Blue), "W 1  L 2  D 1");
+        assert_eq!((score.games(), score.series_winner()), (4, None));
+    }
+
+    #[test]
+    fn a_series_goes_to_the_first_with_a_majority() {
+        let mut score = Score::new(Some(5));
+        assert_eq!(score.first_to(), Some(3));
+        for _ in 0..2 {
+            score.record(&won_by(Turn::Blue));
+        }
+        assert_eq!(score.series_winner(), None);
+        assert_eq!(
+            score.with(&won_by(Turn::Blue)).series_winner(),
+            Some(Turn::Blue)
+        );
+        assert_eq!(score.describe_match(), "Red 0, Blue 2, draws 0");
+        score.reset();
+        assert_eq!((score.games(), score.first_to()), (0, Some(3)));
+    }
+}
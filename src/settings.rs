@@ -0,0 +1,496 @@
+//! Options that can be changed on the settings screen, and the file they
+//! are remembered in.
+//!
+//! The file has one `name = value` line per option, using the values of the
+//! matching command line flags, which override it. Lines that cannot be
+//! read are skipped, so a damaged file never stops the game from starting;
+//! only a file from a different [`VERSION`] is refused as a whole. A file
+//! without a `version` line predates it and is read as version 1. Options
+//! added since are simply missing from older files of the same version and
+//! keep their defaults; a change that reads existing lines differently needs
+//! a new version.
+
+use std::{env, fs, io, path::PathBuf, time::Duration};
+
+use ratatui::symbols::Marker;
+
+use crate::{
+    ai::Difficulty,
+    animation::Speed,
+    config::{Config, MARKERS},
+    game::{PersistenceError, Rules, Turn, MAX_SIZE},
+    theme::Theme,
+};
+
+/// Names of the options, in the order the settings screen lists them.
+pub const ITEMS: [&str; 14] = [
+    "Rows",
+    "Columns",
+    "Line length",
+    "Computer opponent",
+    "You play",
+    "Difficulty",
+    "Thinking time",
+    "Theme",
+    "Marker",
+    "Assist",
+    "Bell",
+    "Animation",
+    "Player panels",
+    "Clock",
+];
+/// Indices into [`ITEMS`], for [`Settings::value`] and [`Settings::change`].
+pub const ROWS: usize = 0;
+pub const COLUMNS: usize = 1;
+pub const LINE: usize = 2;
+pub const AI: usize = 3;
+pub const HUMAN: usize = 4;
+pub const DIFFICULTY: usize = 5;
+pub const AI_TIME: usize = 6;
+pub const THEME: usize = 7;
+pub const MARKER: usize = 8;
+pub const ASSIST: usize = 9;
+pub const BELL: usize = 10;
+pub const ANIMATION: usize = 11;
+pub const PANELS: usize = 12;
+pub const CLOCK: usize = 13;
+
+/// Format of the settings file, written as its `version` line.
+pub const VERSION: u32 = 1;
+
+/// Thinking times offered for the computer, from weakest to strongest.
+const AI_TIMES_MS: [u64; 5] = [250, 500, 1000, 2000, 5000];
+
+/// Chess clock times offered, in minutes per player, after no clocks.
+const CLOCKS_MIN: [u64; 5] = [1, 3, 5, 10, 30];
+
+/// How much the local player is told about winning drops.
+#[derive(Copy, Clone, Default, PartialEq)]
+pub enum Assist {
+    #[default]
+    Off,
+    /// Only that a winning drop exists.
+    Hint,
+    Reveal,
+}
+
+impl Assist {
+    pub const ALL: [Assist; 3] = [Assist::Off, Assist::Hint, Assist::Reveal];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Assist::Off => "off",
+            Assist::Hint => "hint",
+            Assist::Reveal => "reveal",
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct Settings {
+    pub rows: usize,
+    pub columns: usize,
+    /// How many discs in a line win.
+    pub connect: usize,
+    /// Whether the computer plays the side `human` does not.
+    pub ai: bool,
+    /// The side the local player takes against the computer.
+    pub human: Turn,
+    pub difficulty: Difficulty,
+    /// How long the computer thinks on [`Difficulty::Hard`].
+    pub ai_time: Duration,
+    pub theme: Theme,
+    pub marker: Marker,
+    pub assist: Assist,
+    pub bell: bool,
+    pub animation: Speed,
+    /// Whether the player panels are shown below the board.
+    pub panels: bool,
+    /// The time each player has for the game, `None` for no clocks.
+    pub clock: Option<Duration>,
+}
+
+impl Settings {
+    pub fn from_config(config: &Config) -> Settings {
+        Settings {
+            rows: config.rules.height,
+            columns: config.rules.width,
+            connect: config.rules.connect,
+            ai: config.ai,
+            human: config.human,
+            difficulty: config.difficulty,
+            ai_time: config.ai_time,
+            theme: config.theme,
+            marker: config.marker,
+            assist: config.assist,
+            bell: config.bell,
+            animation: config.animation,
+            panels: config.panels,
+            clock: config.clock,
+        }
+    }
+
+    pub fn apply(&self, config: &mut Config) {
+        config.rules = self.rules(config.rules);
+        config.ai = self.ai;
+        config.human = self.human;
+        config.difficulty = self.difficulty;
+        config.ai_time = self.ai_time;
+        config.theme = self.theme;
+        config.marker = self.marker;
+        config.assist = self.assist;
+        config.bell = self.bell;
+        config.animation = self.animation;
+        config.panels = self.panels;
+        config.clock = self.clock;
+    }
+
+    /// `rules` with the board size and line length of these settings.
+    pub fn rules(&self, rules: Rules) -> Rules {
+        Rules {
+            height: self.rows,
+            width: self.columns,
+            connect: self.connect,
+            ..rules
+        }
+    }
+
+    /// The value of [`ITEMS`]`[item]` as shown on the settings screen.
+    pub fn value(&self, item: usize) -> String {
+        let on_off = |on: bool| if on { "on" } else { "off" }.to_string();
+        match item {
+            ROWS => self.rows.to_string(),
+            COLUMNS => self.columns.to_string(),
+            LINE => self.connect.to_string(),
+            AI => on_off(self.ai),
+            HUMAN => self.human.name().to_string(),
+            DIFFICULTY => self.difficulty.name().to_string(),
+            AI_TIME => format!("{} ms", self.ai_time.as_millis()),
+            THEME => self.theme.name.to_string(),
+            MARKER => marker_name(self.marker).to_string(),
+            ASSIST => self.assist.name().to_string(),
+            BELL => on_off(self.bell),
+            ANIMATION => self.animation.name().to_string(),
+            PANELS => on_off(self.panels),
+            CLOCK => match self.clock {
+                Some(limit) => format!("{} min", limit.as_secs() / 60),
+                None => "off".to_string(),
+            },
+            _ => String::new(),
+        }
+    }
+
+    /// Steps [`ITEMS`]`[item]` to its next value, or its previous one when
+    /// not `forward`. Numbers stop at their limits, choices wrap around.
+    pub fn change(&mut self, item: usize, forward: bool) {
+        let step = |value: usize, min: usize, max: usize| {
+            if forward {
+                (value + 1).min(max)
+            } else {
+                value.saturating_sub(1).max(min)
+            }
+        };
+        // A line needs room for two discs one way or the other.
+        let smallest = |other: usize| if other > 1 { 1 } else { 2 };
+        match item {
+            ROWS => self.rows = step(self.rows, smallest(self.columns), MAX_SIZE),
+            COLUMNS => self.columns = step(self.columns, smallest(self.rows), MAX_SIZE),
+            LINE => self.connect = step(self.connect, 2, self.rows.max(self.columns)),
+            AI => self.ai = !self.ai,
+            HUMAN => self.human = self.human.other(),
+            DIFFICULTY => {
+                self.difficulty = cycle(&Difficulty::ALL, |d| *d == self.difficulty, forward)
+            }
+            AI_TIME => {
+                let millis = self.ai_time.as_millis() as u64;
+                let index = AI_TIMES_MS.iter().position(|&time| time >= millis);
+                let index = step(index.unwrap_or(AI_TIMES_MS.len()), 0, AI_TIMES_MS.len() - 1);
+                self.ai_time = Duration::from_millis(AI_TIMES_MS[index]);
+            }
+            THEME => {
+                self.theme = cycle(&Theme::ALL, |theme| theme.name == self.theme.name, forward)
+            }
+            MARKER => {
+                let markers = MARKERS.map(|(_, marker)| marker);
+                self.marker = cycle(&markers, |marker| *marker == self.marker, forward);
+            }
+            ASSIST => self.assist = cycle(&Assist::ALL, |assist| *assist == self.assist, forward),
+            BELL => self.bell = !self.bell,
+            ANIMATION => {
+                self.animation = cycle(&Speed::ALL, |speed| *speed == self.animation, forward)
+            }
+            PANELS => self.panels = !self.panels,
+            CLOCK => {
+                // No clocks sort before the shortest time.
+                let clocks: Vec<_> = std::iter::once(None)
+                    .chain(CLOCKS_MIN.map(|minutes| Some(Duration::from_secs(60 * minutes))))
+                    .collect();
+                let index = clocks.iter().position(|&clock| clock >= self.clock);
+                let index = step(index.unwrap_or(clocks.len() - 1), 0, clocks.len() - 1);
+                self.clock = clocks[index];
+            }
+            _ => {}
+        }
+        // A shrunk board may no longer fit the line.
+        self.connect = self.connect.min(self.rows.max(self.columns));
+    }
+
+    /// Reads the lines of a settings file over these settings, leaving them
+    /// as they are when the file has another version.
+    pub fn read(&mut self, text: &str) -> Result<(), PersistenceError> {
+        let mut read = *self;
+        read.read_lines(text)?;
+        *self = read;
+        Ok(())
+    }
+
+    fn read_lines(&mut self, text: &str) -> Result<(), PersistenceError> {
+        for line in text.lines() {
+            let Some((name, value)) = line.split_once('=') else {
+                continue;
+            };
+            let (name, value) = (name.trim(), value.trim());
+            let number = value.parse::<usize>().ok();
+            let on = match value {
+                "on" => Some(true),
+                "off" => Some(false),
+                _ => None,
+            };
+            match name {
+                "version" if number != Some(VERSION as usize) => {
+                    return Err(PersistenceError::VersionMismatch {
+                        found: value.to_string(),
+                        expected: VERSION,
+                    });
+                }
+                "rows" => self.rows = number.unwrap_or(self.rows),
+                "columns" => self.columns = number.unwrap_or(self.columns),
+                "connect" => self.connect = number.unwrap_or(self.connect),
+                "ai" => self.ai = on.unwrap_or(self.ai),
+                "human" => self.human = Turn::by_name(value).unwrap_or(self.human),
+                "difficulty" => {
+                    if let Some(difficulty) =
+                        Difficulty::ALL.into_iter().find(|d| d.name() == value)
+                    {
+                        self.difficulty = difficulty;
+                    }
+                }
+                "ai-time-ms" => {
+                    if let Some(millis) = number {
+                        self.ai_time = Duration::from_millis(millis as u64);
+                    }
+                }
+                "theme" => self.theme = Theme::by_name(value).unwrap_or(self.theme),
+                "marker" => {
+                    if let Some((_, marker)) = MARKERS.into_iter().find(|(name, _)| *name == value)
+                    {
+                        self.marker = marker;
+                    }
+                }
+                "assist" => {
+                    if let Some(assist) = Assist::ALL.into_iter().find(|a| a.name() == value) {
+                        self.assist = assist;
+                    }
+                }
+                "bell" => self.bell = on.unwrap_or(self.bell),
+                "panels" => self.panels = on.unwrap_or(self.panels),
+                "clock" if on == Some(false) => self.clock = None,
+                "clock" => {
+                    if let Some(minutes) = number.filter(|&minutes| minutes > 0) {
+                        self.clock = Some(Duration::from_secs(60 * minutes as u64));
+                    }
+                }
+                "animation" => {
+                    if let Some(speed) = Speed::ALL.into_iter().find(|s| s.name() == value) {
+                        self.animation = speed;
+                    }
+                }
+                _ => {}
+            }
+        }
+        if self.rules(Rules::default()).validate().is_err() {
+            let rules = Rules::default();
+            (self.rows, self.columns, self.connect) = (rules.height, rules.width, rules.connect);
+        }
+        Ok(())
+    }
+
+    /// The settings as the lines of a settings file.
+    pub fn write(&self) -> String {
+        let on_off = |on: bool| if on { "on" } else { "off" };
+        format!(
+            "version = {VERSION}\nrows = {}\ncolumns = {}\nconnect = {}\nai = {}\nhuman = {}\ndifficulty = {}\nai-time-ms = {}\ntheme = {}\n\
+             marker = {}\nassist = {}\nbell = {}\nanimation = {}\npanels = {}\nclock = {}\n",
+            self.rows,
+            self.columns,
+            self.connect,
+            on_off(self.ai),
+            self.human.name().to_lowercase(),
+            self.difficulty.name(),
+            self.ai_time.as_millis(),
+            self.theme.name,
+            marker_name(self.marker),
+            self.assist.name(),
+            on_off(self.bell),
+            self.animation.name(),
+            on_off(self.panels),
+            self.clock
+                .map_or("off".to_string(), |limit| (limit.as_secs() / 60).to_string())
+        )
+    }
+
+    /// Writes the settings file, returning where it went.
+    pub fn save(&self) -> Result<PathBuf, PersistenceError> {
+        let path = path().ok_or_else(|| io::Error::other("no home directory"))?;
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory)?;
+        }
+        fs::write(&path, self.write())?;
+        Ok(path)
+    }
+}
+
+/// Applies the saved settings, if there are any, to `config`. A missing
+/// file is not an error.
+pub fn load(config: &mut Config) -> Result<(), PersistenceError> {
+    let Some(path) = path() else {
+        return Ok(());
+    };
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err.into()),
+    };
+    let mut settings = Settings::from_config(config);
+    settings.read(&text)?;
+    settings.apply(config);
+    Ok(())
+}
+
+/// `$XDG_CONFIG_HOME/connect4/settings`, falling back to `~/.config`.
+fn path() -> Option<PathBuf> {
+    let base = match env::var_os("XDG_CONFIG_HOME") {
+        Some(base) if !base.is_empty() => PathBuf::from(base),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+    Some(base.join("connect4").join("settings"))
+}
+
+fn marker_name(marker: Marker) -> &'static str {
+    MARKERS
+        .into_iter()
+        .find(|(_, known)| *known == marker)
+        .map_or("halfblock", |(name, _)| name)
+}
+
+/// The entry after the one matching `current` in `all`, or before it when
+/// not `forward`, wrapping around.
+fn cycle<T: Copy>(all: &[T], current: impl Fn(&T) -> bool, forward: bool) -> T {
+    let index = all.iter().position(current).unwrap_or(0);
+    let next = if forward {
+        (index + 1) % all.len()
+    } else {
+        (index + all.len() - 1) % all.len()
+    };
+    all[next]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings_survive_writing_and_reading() {
+        let mut settings = Settings::from_config(&Config::default());
+        settings.change(ROWS, false);
+        settings.change(LINE, true);
+        settings.change(HUMAN, true);
+        settings.change(DIFFICULTY, false);
+        settings.change(AI_TIME, true);
+        settings.change(THEME, false);
+        settings.change(ASSIST, true);
+        settings.change(ANIMATION, true);
+        settings.change(CLOCK, true);
+        settings.change(CLOCK, true);
+        let text = settings.write();
+        // Past the last item there is nothing to change.
+        settings.change(ITEMS.len(), true);
+        assert_eq!(
+            (settings.value(ITEMS.len()), settings.write()),
+            (String::new(), text.clone())
+        );
+        assert!(text.contains("rows = 5\n") && text.contains("connect = 5\n"));
+        assert!(text.contains("human = blue\n") && text.contains("difficulty = medium\n"));
+        assert!(text.contains("ai-time-ms = 2000\n") && text.contains("assist = hint\n"));
+        assert!(text.contains("clock = 3\n"));
+
+        let mut read = Settings::from_config(&Config::default());
+        read.read(&text).unwrap();
+        assert_eq!(read.write(), text);
+    }
+
+    #[test]
+    fn unreadable_lines_are_skipped() {
+        let mut settings = Settings::from_config(&Config::default());
+        let text = "theme = mono\nmarker = sparkles\nbell on\nfuture = 1\nbell = on\n";
+        settings.read(text).unwrap();
+        assert_eq!(settings.value(THEME), "mono");
+        assert_eq!(settings.value(MARKER), "halfblock");
+        assert!(settings.bell);
+
+        settings
+            .read("rows = 2\ncolumns = 2\nconnect = 4\n")
+            .unwrap();
+        assert_eq!(
+            (settings.rows, settings.columns, settings.connect),
+            (6, 7, 4)
+        );
+    }
+
+    #[test]
+    fn files_written_by_earlier_releases_still_load() {
+        // As written before the side against the computer could be chosen.
+        let v1 = "version = 1\nrows = 7\ncolumns = 8\nconnect = 4\nai = on\nai-time-ms = 500\n\
+                  theme = mono\nmarker = braille\nassist = off\nbell = on\nanimation = fast\npanels = off\n";
+        let mut settings = Settings::from_config(&Config::default());
+        settings.read(v1).unwrap();
+        assert_eq!((settings.rows, settings.columns), (7, 8));
+        assert!(settings.ai && settings.human == Turn::Red);
+        assert_eq!(settings.value(MARKER), "braille");
+        assert!(settings.bell && !settings.panels);
+
+        let mut unversioned = Settings::from_config(&Config::default());
+        unversioned.read(&v1.replace("version = 1\n", "")).unwrap();
+        assert_eq!(unversioned.write(), settings.write());
+    }
+
+    #[test]
+    fn files_of_another_version_are_refused_whole() {
+        let mut settings = Settings::from_config(&Config::default());
+        let err = settings.read("theme = mono\nversion = 2\n").unwrap_err();
+        assert!(matches!(
+            err,
+            PersistenceError::VersionMismatch { ref found, expected: VERSION } if found == "2"
+        ));
+        assert_eq!(settings.value(THEME), "default");
+        assert!(settings.write().starts_with("version = 1\n"));
+    }
+
+    #[test]
+    fn line_length_follows_a_shrinking_board() {
+        let mut settings = Settings::from_config(&Config::default());
+        for _ in 0..10 {
+            settings.change(COLUMNS, false);
+            settings.change(ROWS, false);
+        }
+        assert_eq!(
+            (settings.rows, settings.columns, settings.connect),
+            (1, 2, 2)
+        );
+        settings.change(COLUMNS, true);
+        settings.change(COLUMNS, true);
+        settings.change(LINE, true);
+        assert_eq!(settings.connect, 3);
+        assert!(settings.rules(Rules::default()).validate().is_ok());
+    }
+}
@@ -0,0 +1,137 @@
+use std::ffi::OsStr;
+
+use ratatui::style::Color;
+
+use crate::game::Turn;
+
+/// All colors used by the UI, so rendering code never hardcodes a palette.
+#[derive(Copy, Clone)]
+pub struct Theme {
+    pub name: &'static str,
+    pub player1: Color,
+    pub player2: Color,
+    pub idle: Color,
+    pub grid: Color,
+    pub highlight: Color,
+    /// Input that cannot be played as typed.
+    pub warning: Color,
+    pub background: Color,
+    /// Whether the players are told apart by symbols and shapes rather than
+    /// by color.
+    pub symbols: bool,
+}
+
+impl Theme {
+    pub const DEFAULT: Theme = Theme {
+        name: "default",
+        player1: Color::Red,
+        player2: Color::Blue,
+        idle: Color::White,
+        grid: Color::Reset,
+        highlight: Color::Reset,
+        warning: Color::Yellow,
+        background: Color::Reset,
+        symbols: false,
+    };
+
+    pub const HIGH_CONTRAST: Theme = Theme {
+        name: "high-contrast",
+        player1: Color::LightRed,
+        player2: Color::LightCyan,
+        idle: Color::DarkGray,
+        grid: Color::White,
+        highlight: Color::Yellow,
+        warning: Color::LightMagenta,
+        background: Color::Black,
+        symbols: false,
+    };
+
+    pub const MONO: Theme = Theme {
+        name: "mono",
+        player1: Color::White,
+        player2: Color::DarkGray,
+        idle: Color::Gray,
+        grid: Color::Gray,
+        highlight: Color::White,
+        warning: Color::White,
+        background: Color::Reset,
+        symbols: false,
+    };
+
+    /// For terminals without color, chosen with `--no-color` or when
+    /// [`color_supported`] says so. It is not one of [`Theme::ALL`], so it is
+    /// never saved.
+    pub const NO_COLOR: Theme = Theme {
+        name: "no-color",
+        // The board canvas leaves out what is drawn in `Reset`.
+        player1: Color::White,
+        player2: Color::White,
+        idle: Color::White,
+        grid: Color::Reset,
+        highlight: Color::Reset,
+        warning: Color::Reset,
+        background: Color::Reset,
+        symbols: true,
+    };
+
+    pub const ALL: [Theme; 3] = [Theme::DEFAULT, Theme::HIGH_CONTRAST, Theme::MONO];
+
+    pub fn by_name(name: &str) -> Option<Theme> {
+        Theme::ALL.into_iter().find(|theme| theme.name == name)
+    }
+
+    pub fn player(&self, turn: Turn) -> Color {
+        match turn {
+            Turn::Red => self.player1,
+            Turn::Blue => self.player2,
+        }
+    }
+
+    /// The character a disc of `turn` is drawn as where discs are text.
+    pub fn disc(&self, turn: Turn) -> &'static str {
+        match (self.symbols, turn) {
+            (false, _) => "●",
+            (true, Turn::Red) => "X",
+            (true, Turn::Blue) => "O",
+        }
+    }
+}
+
+/// Whether the terminal should be drawn in color, given the `NO_COLOR` and
+/// `TERM` environment variables: not when `NO_COLOR` is set to anything
+/// (see <https://no-color.org>) or on a `dumb` terminal.
+pub fn color_supported(no_color: Option<&OsStr>, term: Option<&OsStr>) -> bool {
+    no_color.is_none_or(OsStr::is_empty) && term.is_none_or(|term| term != "dumb")
+}
+
+impl Default for Theme {
+    fn default() -> Theme {
+        Theme::DEFAULT
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn without_color_the_players_differ_in_symbol() {
+        let theme = Theme::NO_COLOR;
+        assert_eq!(theme.player(Turn::Red), theme.player(Turn::Blue));
+        assert_ne!(theme.disc(Turn::Red), theme.disc(Turn::Blue));
+        assert_eq!(
+            Theme::DEFAULT.disc(Turn::Red),
+            Theme::DEFAULT.disc(Turn::Blue)
+        );
+        assert!(Theme::by_name(theme.name).is_none());
+    }
+
+    #[test]
+    fn no_color_and_dumb_terminals_turn_color_off() {
+        let os = |value| Some(OsStr::new(value));
+        assert!(color_supported(None, os("xterm-256color")));
+        assert!(color_supported(os(""), None));
+        assert!(!color_supported(os("1"), os("xterm-256color")));
+        assert!(!color_supported(None, os("dumb")));
+    }
+}
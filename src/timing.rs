@@ -0,0 +1,103 @@
+//! How long drawing takes, for the `--debug` overlay.
+
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+/// Over how long the frame rate is averaged.
+const WINDOW: Duration = Duration::from_secs(1);
+
+/// Draw times and frame rate of the event loop.
+pub struct FrameTimes {
+    /// The time one tick of the event loop may take.
+    budget: Duration,
+    /// When the frames of the last [`WINDOW`] started drawing.
+    draws: VecDeque<Instant>,
+    last_draw: Duration,
+    /// Whether the work since the previous tick took longer than `budget`.
+    over_budget: bool,
+}
+
+impl FrameTimes {
+    pub fn new(budget: Duration) -> FrameTimes {
+        FrameTimes {
+            budget,
+            draws: VecDeque::new(),
+            last_draw: Duration::ZERO,
+            over_budget: false,
+        }
+    }
+
+    /// Records a frame that started drawing at `start` and took `took`.
+    pub fn drawn(&mut self, start: Instant, took: Duration) {
+        self.last_draw = took;
+        self.draws.push_back(start);
+        while self
+            .draws
+            .front()
+            .is_some_and(|&draw| start.duration_since(draw) >= WINDOW)
+        {
+            self.draws.pop_front();
+        }
+    }
+
+    /// Records a tick after `busy` time spent drawing, handling input and
+    /// updating since the previous one, without waiting for input.
+    pub fn ticked(&mut self, busy: Duration) {
+        self.over_budget = busy > self.budget;
+    }
+
+    /// Frames drawn in the second before `now`. Nothing is drawn while
+    /// nothing changes, so this drops to 0 on a still screen.
+    pub fn fps(&self, now: Instant) -> usize {
+        self.draws
+            .iter()
+            .filter(|&&draw| now.duration_since(draw) < WINDOW)
+            .count()
+    }
+
+    /// The overlay line, e.g. `draw 1.2 ms  60 fps  over budget`.
+    pub fn describe(&self, now: Instant) -> String {
+        format!(
+            "draw {:.1} ms  {} fps  {}",
+            self.last_draw.as_secs_f64() * 1000.0,
+            self.fps(now),
+            if self.over_budget {
+                "over budget"
+            } else {
+                "on budget"
+            }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_the_frames_of_the_last_second() {
+        let mut times = FrameTimes::new(Duration::from_millis(16));
+        let start = Instant::now();
+        for frame in 0..30 {
+            times.drawn(
+                start + Duration::from_millis(50 * frame),
+                Duration::from_micros(1500),
+            );
+        }
+        let now = start + Duration::from_millis(50 * 29);
+        assert_eq!(times.fps(now), 20);
+        assert_eq!(times.describe(now), "draw 1.5 ms  20 fps  on budget");
+        assert_eq!(times.fps(now + WINDOW), 0);
+    }
+
+    #[test]
+    fn a_slow_tick_is_over_budget() {
+        let mut times = FrameTimes::new(Duration::from_millis(16));
+        times.ticked(Duration::from_millis(20));
+        assert!(times.describe(Instant::now()).ends_with("over budget"));
+        times.ticked(Duration::from_millis(3));
+        assert!(times.describe(Instant::now()).ends_with("on budget"));
+    }
+}
@@ -0,0 +1,41 @@
+/// One page of the tutorial: a position, an optional column to point at and
+/// the explanation shown below the board.
+pub struct Step {
+    /// Columns (0-based) played from the empty board to reach the position.
+    pub moves: &'static [usize],
+    pub column: Option<usize>,
+    pub text: &'static str,
+}
+
+pub const STEPS: &[Step] = &[
+    Step {
+        moves: &[],
+        column: None,
+        text: "Connect four of your discs in a row - horizontally, vertically or diagonally - to win.",
+    },
+    Step {
+        moves: &[],
+        column: Some(3),
+        text: "Red moves first: type a column number and press Enter. The center is a strong start - drop here.",
+    },
+    Step {
+        moves: &[3],
+        column: None,
+        text: "The disc falls to the lowest empty field of its column. Now it is Blue's turn.",
+    },
+    Step {
+        moves: &[3, 0, 4, 0],
+        column: Some(5),
+        text: "Red has two in a row. Dropping here makes three, open at both ends.",
+    },
+    Step {
+        moves: &[3, 0, 4, 0, 5, 6],
+        column: None,
+        text: "Blue dropped into column 7 - you just blocked a threat! But the other end is still open.",
+    },
+    Step {
+        moves: &[3, 0, 4, 0, 5, 6, 2],
+        column: None,
+        text: "Red connects four and wins. You are ready to play!",
+    },
+];